@@ -0,0 +1,72 @@
+//! Run with `cargo bench --bench parse`, parses a large generated program with `Parser::parse`
+
+use std::time::{Duration, Instant};
+
+use scheme_parser::{tokenize, Parser};
+
+const CHUNK: &str = r#"(define (fib-NAME n)
+  (if (< n 2)
+      n
+      (+ (fib-NAME (- n 1)) (fib-NAME (- n 2)))))
+(define-values (q-NAME r-NAME) (floor/ 17 5))
+(define (safe-NAME thunk)
+  (guard (e ((error-object? e) (error-object-message e))
+            ((string? e) => string-length)
+            (else #f))
+    (let-values (((a b) (values 1 2)) ((c . d) (values 3 4 5)))
+      (receive (x y . z) (values a b c d)
+        (set! x (+ x y))
+        (thunk x z "string \"NAME\"" 1.5 #t)))))
+(begin
+  (define counter-NAME 0)
+  (define (count-NAME) (set! counter-NAME (+ counter-NAME 1)) counter-NAME))
+(define result-NAME ((lambda args (display (fib-NAME 10)) args) q-NAME r-NAME))
+"#;
+
+fn main() {
+    let chunks = std::env::var("CHUNKS")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(500);
+    let source = (0..chunks)
+        .map(|i| CHUNK.replace("NAME", &i.to_string()))
+        .collect::<String>()
+        + "(count-0)\n";
+    let mut parser = Parser::default();
+    // Make sure the input is valid
+    parser.parse(&source, "bench.scm").unwrap();
+    let tokens = tokenize(&source, "bench.scm").unwrap();
+
+    measure("tokenize", &source, || {
+        tokenize(&source, "bench.scm").unwrap();
+    });
+    measure("parse tokens", &source, || {
+        parser.parse_tokens(&tokens, &source, "bench.scm").unwrap();
+    });
+    measure("parse", &source, || {
+        parser.parse(&source, "bench.scm").unwrap();
+    });
+}
+
+fn measure(name: &str, source: &str, mut f: impl FnMut()) {
+    // Warm up
+    f();
+    let iterations = 10;
+    let mut best = Duration::MAX;
+    let mut total = Duration::ZERO;
+    for _ in 0..iterations {
+        let start = Instant::now();
+        f();
+        let elapsed = start.elapsed();
+        best = best.min(elapsed);
+        total += elapsed;
+    }
+    println!(
+        "{} {} bytes: best {:?}, mean {:?} over {} iterations",
+        name,
+        source.len(),
+        best,
+        total / iterations,
+        iterations
+    );
+}
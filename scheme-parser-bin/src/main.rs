@@ -1,23 +1,102 @@
-use std::fs::read_to_string;
+use std::{fs::read_to_string, process::exit};
 
 use clap::Parser;
-use scheme_parser::transformer::datumize;
+use scheme_parser::{
+    lint::{LintReport, Linter},
+    transformer::{cond_expand::Features, datumize},
+    Formatter, UnknownBuiltin, BUILTINS,
+};
 
 #[derive(Parser)]
 struct Args {
-    file_name: String,
+    #[clap(required_unless_present = "list-transformers")]
+    file_name: Option<String>,
     #[clap(short, long)]
     non_colorful: bool,
     #[clap(short, long)]
     token: bool,
     #[clap(short, long)]
     datum: bool,
+    /// Print the formatted source
+    #[clap(long)]
+    fmt: bool,
+    /// Exit with an error if the source is not formatted
+    #[clap(long)]
+    check: bool,
+    /// Print the diagnostics of the lints instead of the AST
+    #[clap(long)]
+    lint: bool,
+    /// Line width of the formatted source
+    #[clap(long, default_value_t = 80)]
+    width: usize,
+    /// Builtin expansions to run, separated by commas, in the order they run
+    #[clap(
+        long,
+        value_name = "BUILTIN",
+        possible_values = BUILTINS,
+        use_value_delimiter = true
+    )]
+    builtins: Option<Vec<String>>,
+    /// Disable a builtin expansion
+    #[clap(long, value_name = "BUILTIN", possible_values = BUILTINS)]
+    without_builtin: Vec<String>,
+    /// Enable a feature for `cond-expand`
+    #[clap(long, value_name = "FEATURE")]
+    feature: Vec<String>,
+    /// How deep expansions could nest
+    #[clap(long)]
+    expansion_limit: Option<usize>,
+    /// List the transformers in the order they run, then exit
+    #[clap(long)]
+    list_transformers: bool,
 }
 fn main() {
     let args = Args::parse();
-    let file_content = read_to_string(&args.file_name).unwrap();
-    if args.token {
-        match scheme_parser::tokenize(&file_content, &args.file_name) {
+    let mut builder = scheme_parser::Parser::builder();
+    if let Some(builtins) = &args.builtins {
+        let builtins = builtins.iter().map(String::as_str).collect::<Vec<_>>();
+        builder = or_exit(builder.with_builtins(&builtins));
+    }
+    for builtin in &args.without_builtin {
+        builder = or_exit(builder.without_builtin(builtin));
+    }
+    let features = args
+        .feature
+        .iter()
+        .fold(Features::new(), |features, feature| {
+            features.with_feature(feature)
+        });
+    builder = builder.with_features(features);
+    if let Some(limit) = args.expansion_limit {
+        builder = builder.with_expansion_limit(limit);
+    }
+    if args.list_transformers {
+        for name in builder.transformers() {
+            println!("{}", name);
+        }
+        return;
+    }
+    let file_name = args.file_name.unwrap();
+    let file_content = read_to_string(&file_name).unwrap();
+    if args.fmt || args.check {
+        match Formatter::new()
+            .with_width(args.width)
+            .format(&file_content, &file_name)
+        {
+            Ok(formatted) if args.check => {
+                if formatted != file_content {
+                    eprintln!("{} is not formatted", file_name);
+                    exit(1);
+                }
+            }
+            Ok(formatted) => print!("{}", formatted),
+            Err(error) => {
+                print!("{}", error.with_color(!args.non_colorful));
+                exit(1);
+            }
+        }
+    } else if args.token {
+        match scheme_parser::tokenize(&file_content, &file_name) {
             Ok(tokens) => {
                 println!("{:#?}", tokens);
             }
@@ -26,8 +105,8 @@ fn main() {
             }
         }
     } else if args.datum {
-        match scheme_parser::tokenize(&file_content, &args.file_name) {
-            Ok(tokens) => match datumize(&tokens, &file_content, &args.file_name) {
+        match scheme_parser::tokenize(&file_content, &file_name) {
+            Ok(tokens) => match datumize(&tokens, &file_content, &file_name) {
                 Ok(datums) => {
                     println!("{:#?}", datums);
                 }
@@ -38,9 +117,23 @@ fn main() {
             }
         }
     } else {
-        match scheme_parser::Parser::default().parse(&file_content, &args.file_name) {
+        let mut parser = builder.build();
+        match parser.parse(&file_content, &file_name) {
+            Ok(program) if args.lint => {
+                let report =
+                    LintReport::new(Linter::new().check(&program), parser.sources().clone())
+                        .with_expansions(parser.expansions().clone())
+                        .with_color(!args.non_colorful);
+                print!("{}", report);
+                if report.has_errors() {
+                    exit(1);
+                }
+            }
             Ok(program) => {
                 println!("{:#?}", program);
+                if let Some(warnings) = parser.warnings() {
+                    eprint!("{}", warnings.with_color(!args.non_colorful));
+                }
             }
             Err(error) => {
                 print!("{}", error.with_color(!args.non_colorful));
@@ -48,3 +141,11 @@ fn main() {
         }
     }
 }
+
+/// The value, or exit after printing the unknown builtin
+fn or_exit<T>(result: Result<T, UnknownBuiltin>) -> T {
+    result.unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        exit(2)
+    })
+}
@@ -0,0 +1,3 @@
+pub mod closure;
+pub mod exception;
+pub mod scope;
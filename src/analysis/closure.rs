@@ -0,0 +1,181 @@
+use super::scope::{BindingKind, Resolution, Scopes};
+use crate::visit::*;
+use crate::*;
+use std::collections::HashMap;
+
+/// A variable which a procedure refers to but does not bind
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FreeVariable {
+    /// Index of the binding in [`Scopes::bindings`], which is outside of the procedure
+    Bound(usize),
+    /// Not bound in the program, such as `car`
+    Global(String),
+}
+
+/// Variables of an `Expression::Procedure`
+#[derive(Debug)]
+pub struct Closure {
+    pub id: NodeId,
+    pub span: Span,
+    /// Index of the innermost procedure containing this one
+    pub parent: Option<usize>,
+    /// Variables referred to or assigned in the procedure, including its nested procedures, but
+    /// bound outside of it. In the order they first appear.
+    pub free: Vec<FreeVariable>,
+    /// Indices of the free variables which are not bound at the top level, which the closure has
+    /// to store
+    pub captured: Vec<usize>,
+    /// Indices of the captured variables which are assigned by `set!` anywhere, which have to be
+    /// shared by boxes
+    pub mutated: Vec<usize>,
+}
+
+/// Free and captured variables of every procedure of a program
+#[derive(Debug)]
+pub struct Closures {
+    pub scopes: Scopes,
+    /// In the order the procedures are written
+    pub closures: Vec<Closure>,
+    by_id: HashMap<NodeId, usize>,
+}
+
+impl Closures {
+    pub fn analyze(program: &Program) -> Self {
+        let nodes = NodeIndex::new(program);
+        let scopes = Scopes::indexed(program, &nodes);
+        let mut collector = Collector {
+            scopes: &scopes,
+            nodes: &nodes,
+            stack: Vec::new(),
+            procedures: Vec::new(),
+        };
+        collector.visit_program(program);
+        let procedures = collector.procedures;
+        // Is the binding inside the procedure, the node of a parameter is the procedure itself
+        let binds = |binding: usize, procedure: usize| {
+            let node = scopes.bindings[binding].node;
+            std::iter::once(node)
+                .chain(nodes.ancestors(node))
+                .any(|id| id == procedures[procedure].id)
+        };
+        let closures: Vec<_> = procedures
+            .iter()
+            .enumerate()
+            .map(|(index, procedure)| {
+                let mut free = Vec::new();
+                for (name, resolution) in &procedure.references {
+                    let variable = match resolution {
+                        Resolution::Bound(binding) if binds(*binding, index) => continue,
+                        Resolution::Bound(binding) => FreeVariable::Bound(*binding),
+                        Resolution::Free => FreeVariable::Global(name.clone()),
+                    };
+                    if !free.contains(&variable) {
+                        free.push(variable);
+                    }
+                }
+                let captured: Vec<_> = free
+                    .iter()
+                    .filter_map(|variable| match variable {
+                        FreeVariable::Bound(binding)
+                            if scopes.bindings[*binding].kind != BindingKind::TopLevel =>
+                        {
+                            Some(*binding)
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                let mutated = captured
+                    .iter()
+                    .copied()
+                    .filter(|binding| {
+                        scopes
+                            .references_to(*binding)
+                            .any(|reference| reference.assignment)
+                    })
+                    .collect();
+                Closure {
+                    id: procedure.id,
+                    span: procedure.span.clone(),
+                    parent: procedure.parent,
+                    free,
+                    captured,
+                    mutated,
+                }
+            })
+            .collect();
+        let by_id = closures
+            .iter()
+            .enumerate()
+            .map(|(index, closure)| (closure.id, index))
+            .collect();
+        Self {
+            scopes,
+            closures,
+            by_id,
+        }
+    }
+    /// The closure of the procedure `id`
+    pub fn closure(&self, id: NodeId) -> Option<&Closure> {
+        self.by_id.get(&id).map(|&index| &self.closures[index])
+    }
+    /// Is the binding captured by any procedure and assigned by `set!`
+    pub fn is_boxed(&self, binding: usize) -> bool {
+        self.closures
+            .iter()
+            .any(|closure| closure.mutated.contains(&binding))
+    }
+}
+
+struct Procedure {
+    id: NodeId,
+    span: Span,
+    parent: Option<usize>,
+    /// Every variable referred to inside
+    references: Vec<(String, Resolution)>,
+}
+
+struct Collector<'a, 'ast> {
+    scopes: &'a Scopes,
+    nodes: &'a NodeIndex<'ast>,
+    /// Indices of the procedures being visited, the innermost last
+    stack: Vec<usize>,
+    procedures: Vec<Procedure>,
+}
+
+impl<'a, 'ast> Visitor<'ast> for Collector<'a, 'ast> {
+    fn visit_expression(&mut self, expr: &'ast Spanned<Expression>) {
+        match &expr.0 {
+            Expression::Primitive(Primitive::Ident(name))
+            | Expression::Assignment((name, _), _) => {
+                self.reference(name, self.nodes.id(expr).unwrap());
+                walk_expression(self, expr);
+            }
+            Expression::Procedure { .. } => {
+                self.stack.push(self.procedures.len());
+                self.procedures.push(Procedure {
+                    id: self.nodes.id(expr).unwrap(),
+                    span: expr.1.clone(),
+                    parent: self.stack.iter().rev().nth(1).copied(),
+                    references: Vec::new(),
+                });
+                walk_expression(self, expr);
+                self.stack.pop();
+            }
+            _ => walk_expression(self, expr),
+        }
+    }
+}
+
+impl Collector<'_, '_> {
+    /// The variable of `node`, or the variable assigned by it
+    fn reference(&mut self, name: &str, node: NodeId) {
+        let Some(resolution) = self.scopes.resolve(node) else {
+            return;
+        };
+        for procedure in &self.stack {
+            self.procedures[*procedure]
+                .references
+                .push((name.to_owned(), resolution));
+        }
+    }
+}
@@ -0,0 +1,303 @@
+use super::scope::{BindingKind, Resolution, Scopes};
+use crate::*;
+
+/// Procedures which raise an exception
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RaiseKind {
+    /// `(raise obj)`
+    Raise,
+    /// `(raise-continuable obj)`
+    RaiseContinuable,
+    /// `(error message irritant ...)`
+    Error,
+}
+
+impl RaiseKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "raise" => Some(RaiseKind::Raise),
+            "raise-continuable" => Some(RaiseKind::RaiseContinuable),
+            "error" => Some(RaiseKind::Error),
+            _ => None,
+        }
+    }
+}
+
+/// Procedures which inspect an error object
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorObjectAccess {
+    /// `(error-object? obj)`
+    Predicate,
+    /// `(error-object-message obj)`
+    Message,
+    /// `(error-object-irritants obj)`
+    Irritants,
+    /// `(read-error? obj)`
+    ReadError,
+    /// `(file-error? obj)`
+    FileError,
+}
+
+impl ErrorObjectAccess {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "error-object?" => Some(ErrorObjectAccess::Predicate),
+            "error-object-message" => Some(ErrorObjectAccess::Message),
+            "error-object-irritants" => Some(ErrorObjectAccess::Irritants),
+            "read-error?" => Some(ErrorObjectAccess::ReadError),
+            "file-error?" => Some(ErrorObjectAccess::FileError),
+            _ => None,
+        }
+    }
+}
+
+/// A `guard` expression
+#[derive(Debug)]
+pub struct Handler {
+    /// The whole `guard` expression
+    pub span: Span,
+    /// Condition variable
+    pub var: Spanned<String>,
+    /// The guarded body, code inside it runs under this handler
+    pub body: Span,
+    /// Index of the handler which this `guard` expression runs under
+    pub parent: Option<usize>,
+}
+
+/// A call to a procedure which raises an exception
+#[derive(Debug)]
+pub struct RaiseSite {
+    pub kind: RaiseKind,
+    pub span: Span,
+    /// Index of the innermost handler which this call runs under
+    pub handler: Option<usize>,
+}
+
+/// A reference to a procedure which inspects an error object, whether it is called, passed as an
+/// argument or a `=>` receiver
+#[derive(Debug)]
+pub struct ErrorObjectUse {
+    pub access: ErrorObjectAccess,
+    pub span: Span,
+    /// Index of the innermost handler which this reference runs under
+    pub handler: Option<usize>,
+    /// Index of the handler whose clauses contain this reference
+    pub clause_of: Option<usize>,
+}
+
+/// Exception handling facts of a program.
+///
+/// Handlers are resolved lexically: a `lambda` written inside a guarded body is considered to
+/// run under that handler, even though it might be called after the `guard` returns. Procedures are
+/// recognized by name unless a parameter or an internal definition hides the name.
+#[derive(Debug, Default)]
+pub struct Exceptions {
+    pub handlers: Vec<Handler>,
+    pub raises: Vec<RaiseSite>,
+    pub error_object_uses: Vec<ErrorObjectUse>,
+}
+
+impl Exceptions {
+    pub fn analyze(program: &Program) -> Self {
+        let nodes = NodeIndex::new(program);
+        let scopes = Scopes::indexed(program, &nodes);
+        let mut collector = Collector {
+            exceptions: Self::default(),
+            scopes: &scopes,
+            nodes: &nodes,
+        };
+        for (library, _) in &program.libraries {
+            for (declaration, _) in &library.declarations {
+                if let LibraryDeclaration::Begin { defs, exprs } = declaration {
+                    for def in defs {
+                        collector.def(def, None, None);
+                    }
+                    for expr in exprs {
+                        collector.expr(expr, None, None);
+                    }
+                }
+            }
+        }
+        if let Some(body) = &program.body {
+            collector.body(body, None, None);
+        }
+        collector.exceptions
+    }
+    /// Index of the innermost handler which the code at `offset` runs under
+    pub fn handler_at(&self, offset: usize) -> Option<usize> {
+        self.handlers
+            .iter()
+            .enumerate()
+            .filter(|(_, handler)| handler.body.contains(&offset))
+            .max_by_key(|(_, handler)| handler.body.start)
+            .map(|(i, _)| i)
+    }
+    /// Is the code at `span` runs under any handler
+    pub fn is_guarded(&self, span: &Span) -> bool {
+        self.handler_at(span.start).is_some()
+    }
+    /// Calls which raise an exception without any handler
+    pub fn unguarded_raises(&self) -> impl Iterator<Item = &RaiseSite> {
+        self.raises.iter().filter(|raise| raise.handler.is_none())
+    }
+}
+
+struct Collector<'a, 'ast> {
+    exceptions: Exceptions,
+    scopes: &'a Scopes,
+    nodes: &'a NodeIndex<'ast>,
+}
+
+impl<'ast> Collector<'_, 'ast> {
+    /// Is the variable `expr` not bound in the program or bound at the top level, rather than a
+    /// local variable which hides the builtin procedure of the same name
+    fn is_global(&self, expr: &'ast Spanned<Expression>) -> bool {
+        let id = self
+            .nodes
+            .id(expr)
+            .expect("every node of the indexed program has an id");
+        match self.scopes.resolve(id) {
+            Some(Resolution::Bound(binding)) => {
+                self.scopes.bindings[binding].kind == BindingKind::TopLevel
+            }
+            _ => true,
+        }
+    }
+    fn body(
+        &mut self,
+        body: &'ast ProcedureBody,
+        handler: Option<usize>,
+        clause_of: Option<usize>,
+    ) {
+        for def in &body.defs {
+            self.def(def, handler, clause_of);
+        }
+        for expr in body.exprs.iter().chain(Some(&body.last_expr)) {
+            self.expr(expr, handler, clause_of);
+        }
+    }
+    fn def(
+        &mut self,
+        (def, _): &'ast Spanned<Definition>,
+        handler: Option<usize>,
+        clause_of: Option<usize>,
+    ) {
+        match def {
+            Definition::Variable(_, expr) | Definition::Values(_, expr) => {
+                self.expr(expr, handler, clause_of)
+            }
+            Definition::Begin(defs) => {
+                for def in defs {
+                    self.def(def, handler, clause_of);
+                }
+            }
+        }
+    }
+    fn reference(
+        &mut self,
+        ident: &str,
+        expr: &'ast Spanned<Expression>,
+        handler: Option<usize>,
+        clause_of: Option<usize>,
+    ) {
+        let access = ErrorObjectAccess::from_name(ident).filter(|_| self.is_global(expr));
+        if let Some(access) = access {
+            self.exceptions.error_object_uses.push(ErrorObjectUse {
+                access,
+                span: expr.1.clone(),
+                handler,
+                clause_of,
+            });
+        }
+    }
+    fn expr(
+        &mut self,
+        node @ (expr, span): &'ast Spanned<Expression>,
+        handler: Option<usize>,
+        clause_of: Option<usize>,
+    ) {
+        match expr {
+            Expression::ProcedureCall { operator, args } => {
+                if let Expression::Primitive(Primitive::Ident(ident)) = &operator.0 {
+                    let kind = RaiseKind::from_name(ident).filter(|_| self.is_global(operator));
+                    if let Some(kind) = kind {
+                        self.exceptions.raises.push(RaiseSite {
+                            kind,
+                            span: span.clone(),
+                            handler,
+                        });
+                    }
+                }
+                self.expr(operator, handler, clause_of);
+                for arg in args {
+                    self.expr(arg, handler, clause_of);
+                }
+            }
+            Expression::Procedure { body, .. } => self.body(body, handler, clause_of),
+            Expression::Conditional {
+                test,
+                conseq,
+                alter,
+            } => {
+                self.expr(test, handler, clause_of);
+                self.expr(conseq, handler, clause_of);
+                if let Some(alter) = alter {
+                    self.expr(alter, handler, clause_of);
+                }
+            }
+            Expression::Assignment(_, expr) => self.expr(expr, handler, clause_of),
+            Expression::LetValues { bindings, body, .. } => {
+                for (ValuesBinding(_, expr), _) in bindings {
+                    self.expr(expr, handler, clause_of);
+                }
+                self.body(body, handler, clause_of);
+            }
+            Expression::Receive { expr, body, .. } => {
+                self.expr(expr, handler, clause_of);
+                self.body(body, handler, clause_of);
+            }
+            Expression::Begin { defs, exprs } => {
+                for def in defs {
+                    self.def(def, handler, clause_of);
+                }
+                for expr in exprs {
+                    self.expr(expr, handler, clause_of);
+                }
+            }
+            Expression::Guard { var, clauses, body } => {
+                let index = self.exceptions.handlers.len();
+                self.exceptions.handlers.push(Handler {
+                    span: span.clone(),
+                    var: var.clone(),
+                    body: body.span(),
+                    parent: handler,
+                });
+                // Clauses run with the continuation and dynamic environment of `guard`
+                for (clause, _) in clauses {
+                    match clause {
+                        CondClause::Test { test, body } => {
+                            self.expr(test, handler, Some(index));
+                            for expr in body {
+                                self.expr(expr, handler, Some(index));
+                            }
+                        }
+                        CondClause::Arrow { test, receiver } => {
+                            self.expr(test, handler, Some(index));
+                            self.expr(receiver, handler, Some(index));
+                        }
+                        CondClause::Else(body) => {
+                            for expr in body {
+                                self.expr(expr, handler, Some(index));
+                            }
+                        }
+                    }
+                }
+                self.body(body, Some(index), clause_of);
+            }
+            Expression::Primitive(Primitive::Ident(ident)) => {
+                self.reference(ident, node, handler, clause_of)
+            }
+            Expression::Primitive(_) | Expression::Quote(_) | Expression::Error => (),
+        }
+    }
+}
@@ -0,0 +1,331 @@
+use crate::visit::*;
+use crate::*;
+use std::collections::HashMap;
+
+/// How a name is bound
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BindingKind {
+    /// A formal parameter of `lambda`, `let-values` or `receive`, or a `guard` variable
+    Parameter,
+    /// A definition in a procedure body
+    Internal,
+    /// A definition at the top level of the program or a library, including the ones spliced
+    /// from `begin`
+    TopLevel,
+}
+
+/// A binding site
+#[derive(Debug)]
+pub struct Binding {
+    pub name: Spanned<String>,
+    pub kind: BindingKind,
+    /// The definition, or the expression of the binding form
+    pub node: NodeId,
+    /// Index of the scope, every library, body, binding form and clause of `let*-values` has its
+    /// own one
+    pub scope: usize,
+    /// Index of the binding of the same name in an enclosing scope, which this one hides
+    pub shadows: Option<usize>,
+}
+
+/// What a variable refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Resolution {
+    /// Index of the binding
+    Bound(usize),
+    /// Not bound in the program, an imported or builtin name
+    Free,
+}
+
+/// A use of a variable
+#[derive(Debug)]
+pub struct Reference {
+    pub name: Spanned<String>,
+    /// The variable expression, or the `set!` which assigns it
+    pub node: NodeId,
+    pub resolution: Resolution,
+    /// Is it the variable assigned by `set!`
+    pub assignment: bool,
+}
+
+/// Lexical scopes of a program, which link every variable to its binding site.
+///
+/// Definitions of a body are visible in the whole body, as `letrec*` does. A name defined twice
+/// in the same scope resolves to the last definition. The top level of every library is a scope
+/// of its own.
+#[derive(Debug, Default)]
+pub struct Scopes {
+    pub bindings: Vec<Binding>,
+    pub references: Vec<Reference>,
+    /// Indices of the references by their nodes
+    by_node: HashMap<NodeId, usize>,
+}
+
+impl Scopes {
+    pub fn analyze(program: &Program) -> Self {
+        Self::indexed(program, &NodeIndex::new(program))
+    }
+    /// Analyze with the node ids of `nodes`, an error if it is not the index of `program`
+    pub fn with_nodes<'ast>(
+        program: &'ast Program,
+        nodes: &NodeIndex<'ast>,
+    ) -> Result<Self, ForeignIndex> {
+        if !nodes.indexes(program) {
+            return Err(ForeignIndex);
+        }
+        Ok(Self::indexed(program, nodes))
+    }
+    /// Analyze with `nodes`, which is known to index `program`
+    pub(crate) fn indexed<'ast>(program: &'ast Program, nodes: &NodeIndex<'ast>) -> Self {
+        let mut resolver = Resolver {
+            nodes,
+            scopes: Self::default(),
+            stack: Vec::new(),
+            scope_count: 0,
+        };
+        resolver.visit_program(program);
+        resolver.scopes
+    }
+    /// The reference of the variable expression or the `set!` of `node`
+    pub fn reference(&self, node: NodeId) -> Option<&Reference> {
+        self.by_node.get(&node).map(|&i| &self.references[i])
+    }
+    /// What the variable of `node` refers to
+    pub fn resolve(&self, node: NodeId) -> Option<Resolution> {
+        self.reference(node).map(|reference| reference.resolution)
+    }
+    /// Index of the binding whose name is at `span`
+    pub fn binding_at(&self, span: &Span) -> Option<usize> {
+        self.bindings
+            .iter()
+            .position(|binding| binding.name.1 == *span)
+    }
+    /// References to the binding of `index`
+    pub fn references_to(&self, index: usize) -> impl Iterator<Item = &Reference> {
+        self.references
+            .iter()
+            .filter(move |reference| reference.resolution == Resolution::Bound(index))
+    }
+    /// References to names which are not bound in the program
+    pub fn free_references(&self) -> impl Iterator<Item = &Reference> {
+        self.references
+            .iter()
+            .filter(|reference| reference.resolution == Resolution::Free)
+    }
+}
+
+struct Resolver<'a, 'ast> {
+    nodes: &'a NodeIndex<'ast>,
+    scopes: Scopes,
+    /// Innermost scope last, its index and the names to indices of bindings
+    stack: Vec<(usize, HashMap<String, usize>)>,
+    /// Count of the scopes entered
+    scope_count: usize,
+}
+
+impl<'ast> Resolver<'_, 'ast> {
+    fn bind(&mut self, name: &Spanned<String>, kind: BindingKind, node: NodeId) {
+        let index = self.scopes.bindings.len();
+        let ((scope, names), outer) = self.stack.split_last_mut().unwrap();
+        let shadows = outer
+            .iter()
+            .rev()
+            .find_map(|(_, names)| names.get(&name.0))
+            .copied();
+        names.insert(name.0.clone(), index);
+        self.scopes.bindings.push(Binding {
+            name: name.clone(),
+            kind,
+            node,
+            scope: *scope,
+            shadows,
+        });
+    }
+    fn bind_formals(&mut self, (formals, _): &Spanned<Formals>, node: NodeId) {
+        for arg in formals.args.iter().chain(&formals.rest) {
+            self.bind(arg, BindingKind::Parameter, node);
+        }
+    }
+    fn reference(&mut self, name: &str, span: &Span, assignment: bool, node: NodeId) {
+        let resolution = self
+            .stack
+            .iter()
+            .rev()
+            .find_map(|(_, names)| names.get(name))
+            .map_or(Resolution::Free, |&index| Resolution::Bound(index));
+        self.scopes
+            .by_node
+            .insert(node, self.scopes.references.len());
+        self.scopes.references.push(Reference {
+            name: (name.to_owned(), span.clone()),
+            node,
+            resolution,
+            assignment,
+        });
+    }
+    /// Bind the definitions before visiting them, so they are visible in the whole scope
+    fn define(&mut self, def: &'ast Spanned<Definition>, kind: BindingKind) {
+        let node = self.id(def);
+        match &def.0 {
+            Definition::Variable(name, _) => self.bind(name, kind, node),
+            Definition::Values((formals, _), _) => {
+                for name in formals.args.iter().chain(&formals.rest) {
+                    self.bind(name, kind, node);
+                }
+            }
+            Definition::Begin(defs) => {
+                for def in defs {
+                    self.define(def, kind);
+                }
+            }
+        }
+    }
+    /// Definitions spliced from the `begin` at the top level
+    fn define_spliced(
+        &mut self,
+        exprs: impl IntoIterator<Item = &'ast Spanned<Expression>>,
+        kind: BindingKind,
+    ) {
+        for (expr, _) in exprs {
+            if let Expression::Begin { defs, exprs } = expr {
+                for def in defs {
+                    self.define(def, kind);
+                }
+                self.define_spliced(exprs, kind);
+            }
+        }
+    }
+    fn id(&self, node: impl Into<Node<'ast>>) -> NodeId {
+        self.nodes
+            .id(node)
+            .expect("every node of the indexed program has an id")
+    }
+    /// Visit the clauses of `let*-values` from the first of `bindings`, each in a scope nested in
+    /// the previous one, then the body
+    fn sequential(
+        &mut self,
+        bindings: &'ast [Spanned<ValuesBinding>],
+        body: &'ast ProcedureBody,
+        node: NodeId,
+    ) {
+        match bindings.split_first() {
+            Some(((ValuesBinding(formals, init), _), rest)) => {
+                self.visit_expression(init);
+                self.scope(|resolver| {
+                    resolver.bind_formals(formals, node);
+                    resolver.sequential(rest, body, node);
+                });
+            }
+            None => self.visit_body(body),
+        }
+    }
+    /// Visit `f` in a new scope
+    fn scope(&mut self, f: impl FnOnce(&mut Self)) {
+        self.stack.push((self.scope_count, HashMap::new()));
+        self.scope_count += 1;
+        f(self);
+        self.stack.pop();
+    }
+}
+
+impl<'ast> Visitor<'ast> for Resolver<'_, 'ast> {
+    fn visit_program(&mut self, program: &'ast Program) {
+        for library in &program.libraries {
+            self.visit_library(library);
+        }
+        self.scope(|resolver| {
+            if let Some(body) = &program.body {
+                for def in &body.defs {
+                    resolver.define(def, BindingKind::TopLevel);
+                }
+                resolver.define_spliced(
+                    body.exprs.iter().chain(Some(&body.last_expr)),
+                    BindingKind::TopLevel,
+                );
+                walk_body(resolver, body);
+            }
+        });
+    }
+    fn visit_library(&mut self, library: &'ast Spanned<Library>) {
+        self.scope(|resolver| {
+            for (declaration, _) in &library.0.declarations {
+                if let LibraryDeclaration::Begin { defs, exprs } = declaration {
+                    for def in defs {
+                        resolver.define(def, BindingKind::TopLevel);
+                    }
+                    resolver.define_spliced(exprs, BindingKind::TopLevel);
+                }
+            }
+            walk_library(resolver, library);
+        });
+    }
+    fn visit_body(&mut self, body: &'ast ProcedureBody) {
+        self.scope(|resolver| {
+            for def in &body.defs {
+                resolver.define(def, BindingKind::Internal);
+            }
+            walk_body(resolver, body);
+        });
+    }
+    fn visit_expression(&mut self, expr: &'ast Spanned<Expression>) {
+        let node = self.id(expr);
+        match &expr.0 {
+            Expression::Primitive(Primitive::Ident(name)) => {
+                self.reference(name, &expr.1, false, node)
+            }
+            Expression::Procedure { args, rest, body } => self.scope(|resolver| {
+                for arg in args.iter().chain(rest) {
+                    resolver.bind(arg, BindingKind::Parameter, node);
+                }
+                resolver.visit_body(body);
+            }),
+            Expression::Assignment((name, span), value) => {
+                self.reference(name, span, true, node);
+                self.visit_expression(value);
+            }
+            Expression::LetValues {
+                sequential: true,
+                bindings,
+                body,
+            } => self.sequential(bindings, body, node),
+            Expression::LetValues {
+                sequential: false,
+                bindings,
+                body,
+            } => {
+                // Every `let-values` initializer is outside of the formals
+                for (ValuesBinding(_, init), _) in bindings {
+                    self.visit_expression(init);
+                }
+                self.scope(|resolver| {
+                    for (ValuesBinding(formals, _), _) in bindings {
+                        resolver.bind_formals(formals, node);
+                    }
+                    resolver.visit_body(body);
+                });
+            }
+            Expression::Receive {
+                formals,
+                expr,
+                body,
+            } => {
+                self.visit_expression(expr);
+                self.scope(|resolver| {
+                    resolver.bind_formals(formals, node);
+                    resolver.visit_body(body);
+                });
+            }
+            Expression::Guard { var, clauses, body } => {
+                // The variable is bound in the clauses, not in the guarded body
+                self.scope(|resolver| {
+                    resolver.bind(var, BindingKind::Parameter, node);
+                    for clause in clauses {
+                        resolver.visit_cond_clause(clause);
+                    }
+                });
+                self.visit_body(body);
+            }
+            _ => walk_expression(self, expr),
+        }
+    }
+}
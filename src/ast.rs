@@ -1,19 +1,127 @@
 use crate::*;
 use derive_more::From;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ProcedureBody {
     pub defs: Vec<Spanned<Definition>>,
     pub exprs: Vec<Spanned<Expression>>,
     pub last_expr: Spanned<Expression>,
 }
 
-pub type Program = ProcedureBody;
+impl ProcedureBody {
+    /// From the first definition or expression to the last expression
+    pub fn span(&self) -> Span {
+        let start = self
+            .defs
+            .first()
+            .map(|(_, span)| span.start)
+            .or_else(|| self.exprs.first().map(|(_, span)| span.start))
+            .unwrap_or(self.last_expr.1.start);
+        start..self.last_expr.1.end
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Program {
+    pub libraries: Vec<Spanned<Library>>,
+    /// The `import` declarations, each of its import sets
+    pub imports: Vec<Spanned<Vec<Spanned<ImportSet>>>>,
+    /// Could be omitted if the source only defines libraries
+    pub body: Option<ProcedureBody>,
+}
+
+impl Program {
+    /// From the first library or import to the end of the body, empty if there is nothing
+    pub fn span(&self) -> Span {
+        let spans = self
+            .libraries
+            .iter()
+            .map(|(_, span)| span.clone())
+            .chain(self.imports.iter().map(|(_, span)| span.clone()))
+            .chain(self.body.as_ref().map(ProcedureBody::span));
+        spans
+            .reduce(|all, span| all.start.min(span.start)..all.end.max(span.end))
+            .unwrap_or(0..0)
+    }
+}
+
+/// `(define-library name declarations ...)`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Library {
+    pub name: Spanned<LibraryName>,
+    pub declarations: Vec<Spanned<LibraryDeclaration>>,
+}
+
+/// Identifiers and exact non-negative integers, such as `(scheme base)` or `(srfi 1)`
+pub type LibraryName = Vec<Spanned<Primitive>>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LibraryDeclaration {
+    /// `(export spec ...)`
+    Export(Vec<Spanned<ExportSpec>>),
+    /// `(import set ...)`
+    Import(Vec<Spanned<ImportSet>>),
+    /// `(begin defs ... exprs ...)`, unlike a procedure body it could be empty
+    Begin {
+        defs: Vec<Spanned<Definition>>,
+        exprs: Vec<Spanned<Expression>>,
+    },
+    /// `(include file ...)` or `(include-ci file ...)`
+    Include {
+        case_insensitive: bool,
+        files: Vec<Spanned<String>>,
+    },
+    /// `(include-library-declarations file ...)`
+    IncludeLibraryDeclarations(Vec<Spanned<String>>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ExportSpec {
+    Ident(String),
+    /// `(rename internal external)`
+    Rename(Rename),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ImportSet {
+    Library(LibraryName),
+    /// `(only set ident ...)`
+    Only(Box<Spanned<ImportSet>>, Vec<Spanned<String>>),
+    /// `(except set ident ...)`
+    Except(Box<Spanned<ImportSet>>, Vec<Spanned<String>>),
+    /// `(prefix set prefix)`
+    Prefix(Box<Spanned<ImportSet>>, Spanned<String>),
+    /// `(rename set (from to) ...)`
+    Rename(Box<Spanned<ImportSet>>, Vec<Spanned<Rename>>),
+}
 
-#[derive(Debug)]
-pub struct Definition(pub Spanned<String>, pub Spanned<Expression>);
+/// `(from to)`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Rename(pub Spanned<String>, pub Spanned<String>);
 
-#[derive(Debug, From)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Definition {
+    /// `(define name expr)` or `(define (name args ...) body ...)`
+    Variable(Spanned<String>, Spanned<Expression>),
+    /// `(define-values formals expr)`
+    Values(Spanned<Formals>, Spanned<Expression>),
+    /// `(begin def ...)`, the definitions belong to the enclosing scope
+    Begin(Vec<Spanned<Definition>>),
+}
+
+/// Formal parameters, `(args ...)`, `(args ... . rest)` or `rest`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Formals {
+    pub args: Vec<Spanned<String>>,
+    /// Binds to a list of the remaining arguments
+    pub rest: Option<Spanned<String>>,
+}
+
+/// `(formals expr)` in `let-values` and `let*-values`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ValuesBinding(pub Spanned<Formals>, pub Spanned<Expression>);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, From)]
 pub enum Expression {
     ProcedureCall {
         operator: Box<Spanned<Expression>>,
@@ -21,9 +129,13 @@ pub enum Expression {
     },
     #[from(forward)]
     Primitive(Primitive),
+    /// `(quote datum)` or `'datum`, whose datum is data rather than code
+    Quote(Spanned<Datum>),
     Procedure {
         /// Arguments
         args: Vec<Spanned<String>>,
+        /// Rest argument
+        rest: Option<Spanned<String>>,
         /// Procedure Body
         body: Box<ProcedureBody>,
     },
@@ -36,5 +148,51 @@ pub enum Expression {
         alter: Option<Box<Spanned<Expression>>>,
     },
     Assignment(Spanned<String>, Box<Spanned<Expression>>),
+    /// `(let-values (bindings ...) body ...)` or `(let*-values (bindings ...) body ...)`
+    LetValues {
+        /// Is `let*-values`, each binding could see the previous ones
+        sequential: bool,
+        bindings: Vec<Spanned<ValuesBinding>>,
+        body: Box<ProcedureBody>,
+    },
+    /// `(receive formals expr body ...)`
+    Receive {
+        formals: Spanned<Formals>,
+        expr: Box<Spanned<Expression>>,
+        body: Box<ProcedureBody>,
+    },
+    /// `(begin expr ...)`
+    Begin {
+        /// Only a top level `begin` could contain definitions, which are spliced into the top level
+        /// scope
+        defs: Vec<Spanned<Definition>>,
+        /// Empty only if it is a top level `begin` of definitions
+        exprs: Vec<Spanned<Expression>>,
+    },
+    Guard {
+        /// Condition variable
+        var: Spanned<String>,
+        /// Handler clauses
+        clauses: Vec<Spanned<CondClause>>,
+        /// Guarded body
+        body: Box<ProcedureBody>,
+    },
     Error,
 }
+
+/// A `cond`-style clause.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CondClause {
+    /// `(test expr ...)`
+    Test {
+        test: Spanned<Expression>,
+        body: Vec<Spanned<Expression>>,
+    },
+    /// `(test => receiver)`
+    Arrow {
+        test: Spanned<Expression>,
+        receiver: Spanned<Expression>,
+    },
+    /// `(else expr ...)`
+    Else(Vec<Spanned<Expression>>),
+}
@@ -10,15 +10,118 @@ pub enum Datum {
     List(Vec<Spanned<Datum>>),
 }
 
+/// Print as `write` does, which `read_datum` reads back. Datums are trees, so there is no shared
+/// structure to label.
 impl Display for Datum {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.print(f, true)
+    }
+}
+
+impl Datum {
+    /// Print as `display` does, strings are printed without quotes or escapes
+    pub fn display(&self) -> impl Display + '_ {
+        Printed(self, false)
+    }
+    /// A short form for diagnostics, which prints only the head of lists
+    pub fn summary(&self) -> impl Display + '_ {
+        Summary(self)
+    }
+    fn print(&self, f: &mut std::fmt::Formatter<'_>, write: bool) -> std::fmt::Result {
         match self {
+            Datum::Error => write!(f, "#<error>"),
+            Datum::Keyword(keyword) => write!(f, "{}", keyword),
+            Datum::Primitive(Primitive::Ident(ident)) if write => write_symbol(f, ident),
+            Datum::Primitive(Primitive::String(s)) if !write => write!(f, "{}", s),
+            Datum::Primitive(p) => write!(f, "{}", p),
+            Datum::List(list) => {
+                if let [(Datum::Primitive(Primitive::Ident(head)), _), (datum, _)] = list.as_slice()
+                {
+                    if let Some((prefix, _)) = ABBREVIATIONS.iter().find(|(_, name)| name == head) {
+                        write!(f, "{}", prefix)?;
+                        return datum.print(f, write);
+                    }
+                }
+                write!(f, "(")?;
+                for (i, (datum, _)) in list.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    datum.print(f, write)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+struct Printed<'a>(&'a Datum, bool);
+
+impl Display for Printed<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.print(f, self.1)
+    }
+}
+
+struct Summary<'a>(&'a Datum);
+
+impl Display for Summary<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
             Datum::Error => write!(f, "<error>"),
             Datum::Keyword(keyword) => write!(f, "{}", keyword),
             Datum::Primitive(p) => write!(f, "{}", p),
-            Datum::List(_) => write!(f, "( ... )"),
+            Datum::List(list) => match list.first() {
+                Some((head, _)) => write!(f, "({} ...)", Summary(head)),
+                None => write!(f, "()"),
+            },
+        }
+    }
+}
+
+/// Write `symbol` in bars if it would not be read back as the same identifier
+fn write_symbol(f: &mut std::fmt::Formatter<'_>, symbol: &str) -> std::fmt::Result {
+    let mut chars = symbol.chars();
+    // Such as `1+` or `-1a`, which are read as numbers
+    let numeric = match (chars.next(), chars.next()) {
+        (Some('+' | '-'), Some(c)) | (Some(c), _) => c.is_ascii_digit(),
+        (None, _) => false,
+    };
+    let plain = !symbol.is_empty()
+        && !numeric
+        && (symbol == "..."
+            || symbol
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || EXTENDED_IDENTIFIER_CHARS.contains(c)));
+    if plain {
+        return write!(f, "{}", symbol);
+    }
+    write!(f, "|")?;
+    for c in symbol.chars() {
+        match c {
+            '|' => write!(f, "\\|")?,
+            '\\' => write!(f, "\\\\")?,
+            c if c.is_control() => write!(f, "\\x{:x};", c as u32)?,
+            c => write!(f, "{}", c)?,
         }
     }
+    write!(f, "|")
+}
+
+/// Prefixes which abbreviate a list of two datums, `'datum` is read as `(quote datum)`
+pub const ABBREVIATIONS: &[(&str, &str)] = &[
+    ("'", "quote"),
+    ("`", "quasiquote"),
+    (",", "unquote"),
+    (",@", "unquote-splicing"),
+];
+
+/// The identifier which `prefix` abbreviates
+pub fn abbreviation(prefix: &str) -> Option<&'static str> {
+    ABBREVIATIONS
+        .iter()
+        .find(|(abbreviation, _)| *abbreviation == prefix)
+        .map(|(_, name)| *name)
 }
 
 pub trait IntoTokens {
@@ -34,7 +137,7 @@ impl IntoTokens for Spanned<Datum> {
             Datum::Primitive(primitive) => vec![(Token::Primitive(primitive), span)],
             Datum::List(datums) => {
                 let mut tokens = vec![(Token::Keyword("("), span.start..span.start + 1)];
-                tokens.extend(datums.into_tokens().into_iter());
+                tokens.extend(datums.into_tokens());
                 tokens.push((Token::Keyword(")"), span.end - 1..span.end));
                 tokens
             }
@@ -50,3 +153,186 @@ where
         self.into_iter().flat_map(|t| t.into_tokens()).collect()
     }
 }
+
+/// Values which `datum!` takes as literals or interpolates, made-up datums span `0..0`
+pub trait IntoDatum {
+    fn into_datum(self) -> Spanned<Datum>;
+}
+
+impl IntoDatum for Spanned<Datum> {
+    fn into_datum(self) -> Spanned<Datum> {
+        self
+    }
+}
+
+impl IntoDatum for Datum {
+    fn into_datum(self) -> Spanned<Datum> {
+        (self, 0..0)
+    }
+}
+
+impl IntoDatum for Primitive {
+    fn into_datum(self) -> Spanned<Datum> {
+        (Datum::Primitive(self), 0..0)
+    }
+}
+
+/// A Scheme string, identifiers are interpolated as `Primitive::Ident`
+impl IntoDatum for &str {
+    fn into_datum(self) -> Spanned<Datum> {
+        Primitive::String(self.to_owned()).into_datum()
+    }
+}
+
+impl IntoDatum for String {
+    fn into_datum(self) -> Spanned<Datum> {
+        Primitive::String(self).into_datum()
+    }
+}
+
+impl IntoDatum for bool {
+    fn into_datum(self) -> Spanned<Datum> {
+        Primitive::Bool(self).into_datum()
+    }
+}
+
+macro_rules! into_integer {
+    ($($t: ty),*) => {
+        $(
+            impl IntoDatum for $t {
+                fn into_datum(self) -> Spanned<Datum> {
+                    Primitive::Integer(self.into()).into_datum()
+                }
+            }
+        )*
+    };
+}
+
+into_integer!(i32, i64, u32, u64, usize, num::BigInt);
+
+/// Panics if `self` is not finite
+impl IntoDatum for f64 {
+    fn into_datum(self) -> Spanned<Datum> {
+        assert!(self.is_finite(), "{} is not a Scheme real", self);
+        let printed = self.to_string();
+        let (int, frac) = printed.split_once('.').unwrap_or((&printed, "0"));
+        Primitive::Real(Real {
+            numer: format!("{}{}", int, frac).parse().unwrap(),
+            denom_log10: frac.len() as u32,
+        })
+        .into_datum()
+    }
+}
+
+/// An identifier read by `datum!`, which could be a keyword
+#[doc(hidden)]
+pub fn symbol(name: &str) -> Spanned<Datum> {
+    match KEYWORDS.iter().find(|keyword| **keyword == name) {
+        Some(keyword) => (Datum::Keyword(keyword), 0..0),
+        None => Primitive::Ident(name.to_owned()).into_datum(),
+    }
+}
+
+/// Build a `Spanned<Datum>` from Scheme syntax written in Rust, such as
+/// `datum!((define x #(unquote value)))`.
+///
+/// - `#(unquote expr)` interpolates anything `IntoDatum`, `#(unquote-splicing expr)` interpolates
+///   every item of an iterator.
+/// - Rust literals are Scheme literals, `#t` and `#f` are booleans. A negative number should be
+///   interpolated, as `-1` is read as `(- 1)`.
+/// - Identifiers are joined across `-`, `->` and `/`, and take a trailing `!`, `?` or `*`, so
+///   `(list->vector x)` and `(set! x 1)` read as in Scheme, while `(a - b)` reads as `(a-b)`.
+///
+/// Made-up datums span `0..0`, interpolated datums keep their spans.
+#[macro_export]
+macro_rules! datum {
+    ($($datum: tt)+) => {{
+        let mut items: ::std::vec::Vec<$crate::Spanned<$crate::Datum>> = ::std::vec::Vec::new();
+        $crate::datum_items!(items; $($datum)+);
+        assert_eq!(items.len(), 1, "datum! takes exactly one datum");
+        items.remove(0)
+    }};
+}
+
+/// Push the datums read from Scheme syntax into `$items`
+#[doc(hidden)]
+#[macro_export]
+macro_rules! datum_items {
+    ($items: ident;) => {};
+    ($items: ident; #(unquote-splicing $e: expr) $($rest: tt)*) => {
+        $items.extend(::std::iter::IntoIterator::into_iter($e).map($crate::IntoDatum::into_datum));
+        $crate::datum_items!($items; $($rest)*);
+    };
+    ($items: ident; #(unquote $e: expr) $($rest: tt)*) => {
+        $items.push($crate::IntoDatum::into_datum($e));
+        $crate::datum_items!($items; $($rest)*);
+    };
+    ($items: ident; #t $($rest: tt)*) => {
+        $items.push($crate::IntoDatum::into_datum(true));
+        $crate::datum_items!($items; $($rest)*);
+    };
+    ($items: ident; #f $($rest: tt)*) => {
+        $items.push($crate::IntoDatum::into_datum(false));
+        $crate::datum_items!($items; $($rest)*);
+    };
+    ($items: ident; ($($list: tt)*) $($rest: tt)*) => {
+        let mut list: ::std::vec::Vec<$crate::Spanned<$crate::Datum>> = ::std::vec::Vec::new();
+        $crate::datum_items!(list; $($list)*);
+        $items.push(($crate::Datum::List(list), 0..0));
+        $crate::datum_items!($items; $($rest)*);
+    };
+    ($items: ident; . $($rest: tt)*) => {
+        $items.push(($crate::Datum::Keyword("."), 0..0));
+        $crate::datum_items!($items; $($rest)*);
+    };
+    ($items: ident; $literal: literal $($rest: tt)*) => {
+        $items.push($crate::IntoDatum::into_datum($literal));
+        $crate::datum_items!($items; $($rest)*);
+    };
+    ($items: ident; $ident: ident $($rest: tt)*) => {
+        $crate::datum_items!(@ident $items; [$ident] $($rest)*);
+    };
+    ($items: ident; $other: tt $($rest: tt)*) => {
+        $items.push($crate::symbol(stringify!($other)));
+        $crate::datum_items!($items; $($rest)*);
+    };
+    (@ident $items: ident; [$($name: tt)*] - $next: ident $($rest: tt)*) => {
+        $crate::datum_items!(@ident $items; [$($name)* - $next] $($rest)*);
+    };
+    (@ident $items: ident; [$($name: tt)*] -> $next: ident $($rest: tt)*) => {
+        $crate::datum_items!(@ident $items; [$($name)* -> $next] $($rest)*);
+    };
+    (@ident $items: ident; [$($name: tt)*] / $next: ident $($rest: tt)*) => {
+        $crate::datum_items!(@ident $items; [$($name)* / $next] $($rest)*);
+    };
+    (@ident $items: ident; [$($name: tt)*] ! $($rest: tt)*) => {
+        $crate::datum_items!(@ident $items; [$($name)* !] $($rest)*);
+    };
+    (@ident $items: ident; [$($name: tt)*] ? $($rest: tt)*) => {
+        $crate::datum_items!(@ident $items; [$($name)* ?] $($rest)*);
+    };
+    (@ident $items: ident; [$($name: tt)*] * $($rest: tt)*) => {
+        $crate::datum_items!(@ident $items; [$($name)* *] $($rest)*);
+    };
+    (@ident $items: ident; [$($name: tt)*] $($rest: tt)*) => {
+        $items.push($crate::symbol(concat!($(stringify!($name)),*)));
+        $crate::datum_items!($items; $($rest)*);
+    };
+}
+
+/// Build a `Spanned<Expression>` as `datum!` builds a datum, panics if it is not an expression
+#[macro_export]
+macro_rules! expr {
+    ($($datum: tt)+) => {
+        $crate::convert_expression($crate::datum!($($datum)+)).unwrap_or_else(|errors| {
+            panic!(
+                "expr! takes an expression: {}",
+                errors
+                    .iter()
+                    .map(|error| format!("{:?}", error))
+                    .collect::<::std::vec::Vec<_>>()
+                    .join(", ")
+            )
+        })
+    };
+}
@@ -3,14 +3,17 @@ use std::{
     hash::Hash,
 };
 
-use ariadne::{Color, Fmt, FnCache, Label, Report, ReportKind, Source};
-use chumsky::prelude::Simple;
+use ariadne::{Color, Fmt, FnCache, Label, Report, ReportBuilder, ReportKind, Source};
+use chumsky::{error::Error as _, prelude::Simple};
+
+use crate::{Expansions, SourceMap, Span, Token};
 
 #[derive(Debug, Default)]
 pub struct ParseError<T: Hash> {
     simple: Vec<Simple<T>>,
-    source: String,
-    source_path: String,
+    warnings: Vec<Simple<T>>,
+    sources: SourceMap,
+    expansions: Expansions,
     type_name: &'static str,
     colorful: bool,
     display_every_expected: bool,
@@ -24,9 +27,10 @@ impl<T: Hash> ParseError<T> {
         type_name: &'static str,
     ) -> ParseError<T> {
         ParseError {
-            source,
-            source_path,
+            sources: SourceMap::new(&source_path, &source),
             simple: e,
+            warnings: Vec::new(),
+            expansions: Expansions::default(),
             type_name,
             colorful: true,
             display_every_expected: true,
@@ -38,7 +42,111 @@ impl<T: Hash> ParseError<T> {
             ..self
         }
     }
+    /// Warnings are rendered after the errors
+    pub fn with_warnings(self, warnings: Vec<Simple<T>>) -> Self {
+        Self { warnings, ..self }
+    }
+    /// Render spans which come from the sources loaded while parsing, such as the files of `include`
+    pub fn with_sources(self, sources: SourceMap) -> Self {
+        Self { sources, ..self }
+    }
+    /// Point out the transformer uses which produced an error, if it is in the expanded code
+    pub fn with_expansions(self, expansions: Expansions) -> Self {
+        Self { expansions, ..self }
+    }
+    fn locate(&self, span: Span) -> (String, Span) {
+        locate(&self.sources, span)
+    }
+}
+
+/// An `else` clause of `form` which is followed by other clauses, as `cond-expand` and `guard`
+/// report it
+pub(crate) fn else_not_last(span: Span, form: &'static str) -> Simple<Token> {
+    Simple::custom(span, "else clause must be the last clause").with_label(form)
+}
+
+/// The path of the source which `span` comes from, and the span relative to that source
+pub(crate) fn locate(sources: &SourceMap, span: Span) -> (String, Span) {
+    match sources.locate(&span) {
+        Some((file, span)) => (file.path.clone(), span),
+        None => (
+            sources
+                .files()
+                .first()
+                .map(|file| file.path.clone())
+                .unwrap_or_default(),
+            span,
+        ),
+    }
+}
+
+/// Label the transformer uses which produced `span`
+pub(crate) fn with_backtrace(
+    report: ReportBuilder<(String, Span)>,
+    span: &Span,
+    expansions: &Expansions,
+    sources: &SourceMap,
+    colorful: bool,
+) -> ReportBuilder<(String, Span)> {
+    expansions
+        .backtrace(span)
+        .into_iter()
+        .fold(report, |report, expansion| {
+            let label = Label::new(locate(sources, expansion.call_site.clone()));
+            report.with_label(if colorful {
+                label
+                    .with_message(format!(
+                        "in expansion of macro {}",
+                        (&expansion.transformer).fg(Color::Blue)
+                    ))
+                    .with_color(Color::Blue)
+            } else {
+                label.with_message(format!("in expansion of macro {}", expansion.transformer))
+            })
+        })
+}
+
+/// Render `report` with the lines of `sources`
+pub(crate) fn write_report(
+    report: ReportBuilder<(String, Span)>,
+    sources: &SourceMap,
+    colorful: bool,
+    f: &mut std::fmt::Formatter<'_>,
+) -> std::fmt::Result {
+    let mut content = Vec::new();
+    if report
+        .with_config(ariadne::Config::default().with_color(colorful))
+        .finish()
+        .write(
+            FnCache::new(
+                (move |id| Err(Box::new(format!("Failed to fetch source '{}'", id)) as _))
+                    as fn(&_) -> _,
+            )
+            .with_sources(
+                sources
+                    .files()
+                    .iter()
+                    .map(|file| {
+                        (
+                            file.path.clone(),
+                            Source::from(if file.source.is_empty() {
+                                " "
+                            } else {
+                                &file.source
+                            }),
+                        )
+                    })
+                    .collect(),
+            ),
+            &mut content,
+        )
+        .is_err()
+    {
+        return Err(std::fmt::Error);
+    }
+    write!(f, "{}", String::from_utf8_lossy(&content))
 }
+
 impl<T: Hash + Eq + Display> std::fmt::Display for ParseError<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         macro_rules! with_color {
@@ -59,8 +167,18 @@ impl<T: Hash + Eq + Display> std::fmt::Display for ParseError<T> {
                 }
             };
         }
-        for error in &self.simple {
-            let report = Report::build(ReportKind::Error, &self.source_path, error.span().start);
+        let errors = self.simple.iter().map(|error| (ReportKind::Error, error));
+        let warnings = self
+            .warnings
+            .iter()
+            .map(|warning| (ReportKind::Warning, warning));
+        for (kind, error) in errors.chain(warnings) {
+            let color = match kind {
+                ReportKind::Warning => Color::Yellow,
+                _ => Color::Red,
+            };
+            let (path, span) = self.locate(error.span());
+            let report = Report::build(kind, path, span.start);
             let report = match error.reason() {
                 chumsky::error::SimpleReason::Unexpected => report
                     .with_message({
@@ -94,16 +212,16 @@ impl<T: Hash + Eq + Display> std::fmt::Display for ParseError<T> {
                         msg
                     })
                     .with_label(with_color!(
-                        Label::new((&self.source_path, error.span())).with_message(format!(
+                        Label::new(self.locate(error.span())).with_message(format!(
                             "Unexpected {}",
                             error
                                 .found()
-                                .map(|c| format!("{} {}", self.type_name, fg!(c, Color::Red)))
+                                .map(|c| format!("{} {}", self.type_name, fg!(c, color)))
                                 .unwrap_or_else(
-                                    || fg!("end of file".to_string(), Color::Red).to_string()
+                                    || fg!("end of file".to_string(), color).to_string()
                                 ),
                         )),
-                        Color::Red
+                        color
                     )),
                 chumsky::error::SimpleReason::Unclosed { span, delimiter } => report
                     .with_message(format!(
@@ -116,24 +234,24 @@ impl<T: Hash + Eq + Display> std::fmt::Display for ParseError<T> {
                         },
                     ))
                     .with_label(with_color!(
-                        Label::new((&self.source_path, span.clone())).with_message(format!(
+                        Label::new(self.locate(span.clone())).with_message(format!(
                             "Unclosed delimiter {}",
                             fg!(delimiter, Color::Yellow)
                         )),
                         Color::Yellow
                     ))
                     .with_label(with_color!(
-                        Label::new((&self.source_path, error.span())).with_message(format!(
+                        Label::new(self.locate(error.span())).with_message(format!(
                             "Must be closed before this {}",
                             fg!(
                                 error
                                     .found()
                                     .map(|c| c.to_string())
                                     .unwrap_or_else(|| "end of file".to_string()),
-                                Color::Red
+                                color
                             )
                         )),
-                        Color::Red
+                        color
                     )),
                 chumsky::error::SimpleReason::Custom(msg) => report
                     .with_message(format!(
@@ -146,40 +264,19 @@ impl<T: Hash + Eq + Display> std::fmt::Display for ParseError<T> {
                         },
                     ))
                     .with_label(with_color!(
-                        Label::new((&self.source_path, error.span()))
-                            .with_message(format!("{}", fg!(msg, Color::Red))),
-                        Color::Red
+                        Label::new(self.locate(error.span()))
+                            .with_message(format!("{}", fg!(msg, color))),
+                        color
                     )),
             };
-            let mut content = Vec::new();
-            if report
-                .with_config(ariadne::Config::default().with_color(self.colorful))
-                .finish()
-                .write(
-                    FnCache::new(
-                        (move |id| Err(Box::new(format!("Failed to fetch source '{}'", id)) as _))
-                            as fn(&_) -> _,
-                    )
-                    .with_sources(
-                        vec![(
-                            &self.source_path,
-                            if self.source.is_empty() {
-                                " "
-                            } else {
-                                &self.source
-                            },
-                        )]
-                        .into_iter()
-                        .map(|(id, s)| (id, Source::from(s)))
-                        .collect(),
-                    ),
-                    &mut content,
-                )
-                .is_err()
-            {
-                return Err(std::fmt::Error);
-            }
-            write!(f, "{}", String::from_utf8_lossy(&content))?;
+            let report = with_backtrace(
+                report,
+                &error.span(),
+                &self.expansions,
+                &self.sources,
+                self.colorful,
+            );
+            write_report(report, &self.sources, self.colorful, f)?;
         }
         Ok(())
     }
@@ -0,0 +1,409 @@
+use crate::*;
+use std::iter::repeat_n;
+
+/// Formats Scheme source, the `scheme-fmt` mode of `scheme-parser-bin`.
+///
+/// A list stays on one line if it fits in the width. Otherwise the bodies of `define`, `lambda`,
+/// `let` and the like are indented by two, and the arguments of other lists are aligned under the
+/// first one. Atoms are written as they are in the source. Comments and blank lines between datums
+/// are kept, at most one blank line in a row. Formatting a formatted source gives the same source.
+#[derive(Debug, Clone)]
+pub struct Formatter {
+    width: usize,
+}
+
+impl Default for Formatter {
+    fn default() -> Self {
+        Self { width: 80 }
+    }
+}
+
+impl Formatter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Break lines to fit in `width` columns if possible, 80 by default
+    pub fn with_width(self, width: usize) -> Self {
+        Self { width }
+    }
+    /// Format `source`, which must be balanced datums
+    pub fn format(&self, source: &str, source_path: &str) -> Result<String, TokenizeOrParseError> {
+        let tokens = tokenize(source, source_path)?;
+        transformer::datumize(&tokens, source, source_path)?;
+        let chars: Vec<_> = source.chars().collect();
+        let mut reader = Reader {
+            chars: &chars,
+            tokens: &tokens,
+            position: 0,
+            offset: 0,
+        };
+        let elements = reader.elements();
+        let mut docs = sequence(None, &elements, &Doc::HardLine, false);
+        if !elements.is_empty() {
+            docs.push(Doc::HardLine);
+        }
+        Ok(print(&Doc::Concat(docs), self.width))
+    }
+}
+
+/// Forms whose body is indented by two, and how many arguments come before the body
+const BODY_FORMS: &[(&str, usize)] = &[
+    ("begin", 0),
+    ("case", 1),
+    ("case-lambda", 0),
+    ("cond", 0),
+    ("define", 1),
+    ("define-library", 1),
+    ("define-record-type", 2),
+    ("define-syntax", 1),
+    ("define-values", 1),
+    ("do", 2),
+    ("guard", 1),
+    ("lambda", 1),
+    ("let", 1),
+    ("let*", 1),
+    ("let*-values", 1),
+    ("let-syntax", 1),
+    ("let-values", 1),
+    ("letrec", 1),
+    ("letrec*", 1),
+    ("letrec-syntax", 1),
+    ("parameterize", 1),
+    ("receive", 2),
+    ("syntax-rules", 1),
+    ("unless", 1),
+    ("when", 1),
+];
+
+/// A datum or a comment as it is written
+enum Node {
+    Atom(String),
+    List(Vec<Element>),
+    /// `'datum`, `` `datum ``, `,datum` or `,@datum`
+    Prefixed(&'static str, Box<Node>),
+    /// `; ...`, trailing if it is on the line of the previous token
+    Comment {
+        text: String,
+        trailing: bool,
+    },
+}
+
+struct Element {
+    node: Node,
+    /// Is there a blank line before it
+    blank_before: bool,
+}
+
+/// Reads the tokens back into nodes, with the comments between them
+struct Reader<'a> {
+    chars: &'a [char],
+    tokens: &'a [Spanned<Token>],
+    /// The next token
+    position: usize,
+    /// The char after the previous token
+    offset: usize,
+}
+
+impl Reader<'_> {
+    /// The comments before the char `end`, and the count of newlines after the last of them
+    fn comments(&mut self, end: usize, elements: &mut Vec<Element>) -> usize {
+        // The start of the source is the start of a line
+        let mut newlines = if self.offset == 0 { 1 } else { 0 };
+        while self.offset < end {
+            match self.chars[self.offset] {
+                '\n' => newlines += 1,
+                ';' => {
+                    let start = self.offset;
+                    while self.offset < end && self.chars[self.offset] != '\n' {
+                        self.offset += 1;
+                    }
+                    let text: String = self.chars[start..self.offset].iter().collect();
+                    elements.push(Element {
+                        node: Node::Comment {
+                            text: text.trim_end().to_owned(),
+                            trailing: newlines == 0,
+                        },
+                        blank_before: newlines >= 2,
+                    });
+                    newlines = 0;
+                    continue;
+                }
+                _ => {}
+            }
+            self.offset += 1;
+        }
+        newlines
+    }
+    /// The elements until the closing parenthesis or the end of tokens
+    fn elements(&mut self) -> Vec<Element> {
+        let mut elements = Vec::new();
+        loop {
+            let end = self
+                .tokens
+                .get(self.position)
+                .map_or(self.chars.len(), |(_, span)| span.start);
+            let newlines = self.comments(end, &mut elements);
+            match self.tokens.get(self.position) {
+                None => return elements,
+                Some((Token::Keyword(")"), span)) => {
+                    self.position += 1;
+                    self.offset = span.end;
+                    return elements;
+                }
+                Some(_) => {
+                    let node = self.node(&mut elements);
+                    elements.push(Element {
+                        node,
+                        blank_before: newlines >= 2,
+                    });
+                }
+            }
+        }
+    }
+    /// The datum of the next token, comments between a prefix and its datum are moved before it
+    fn node(&mut self, elements: &mut Vec<Element>) -> Node {
+        let (token, span) = &self.tokens[self.position];
+        self.position += 1;
+        self.offset = span.end;
+        match token {
+            Token::Keyword("(") => Node::List(self.elements()),
+            Token::Keyword(prefix) if abbreviation(prefix).is_some() => {
+                let end = self.tokens[self.position].1.start;
+                self.comments(end, elements);
+                Node::Prefixed(prefix, Box::new(self.node(elements)))
+            }
+            _ => Node::Atom(self.chars[span.clone()].iter().collect()),
+        }
+    }
+}
+
+/// A document of the pretty printer
+#[derive(Clone)]
+enum Doc {
+    Text(String),
+    /// A space if the group is flat, a newline otherwise
+    Line,
+    /// A newline, the groups around it are never flat
+    HardLine,
+    /// Indent the lines by more columns
+    Nest(usize, Box<Doc>),
+    /// Indent the lines to the current column
+    Align(Box<Doc>),
+    /// Flat if it fits in the rest of the line
+    Group(Box<Doc>),
+    Concat(Vec<Doc>),
+}
+
+fn text(s: &str) -> Doc {
+    Doc::Text(s.to_owned())
+}
+
+/// The separator before `next`, comments end their lines and blank lines are kept
+fn separator(prev: &Element, next: &Element, soft: &Doc) -> Doc {
+    match (&prev.node, &next.node) {
+        (_, Node::Comment { trailing: true, .. }) => text(" "),
+        _ if next.blank_before => Doc::Concat(vec![Doc::HardLine, Doc::HardLine]),
+        (Node::Comment { .. }, _) | (_, Node::Comment { .. }) => Doc::HardLine,
+        _ => soft.clone(),
+    }
+}
+
+/// Could `prev` and `next` be on one line
+fn is_soft(prev: &Element, next: &Element) -> bool {
+    !next.blank_before
+        && !matches!(prev.node, Node::Comment { .. })
+        && !matches!(next.node, Node::Comment { .. })
+}
+
+/// `elements` with the separators before them, the first one follows `prev`
+fn sequence(prev: Option<&Element>, elements: &[Element], soft: &Doc, quoted: bool) -> Vec<Doc> {
+    let mut docs = Vec::new();
+    for (i, element) in elements.iter().enumerate() {
+        if let Some(prev) = i.checked_sub(1).map(|i| &elements[i]).or(prev) {
+            docs.push(separator(prev, element, soft));
+        }
+        docs.push(element.node.doc(quoted));
+    }
+    docs
+}
+
+/// The newline before the closing parenthesis if the last element is a comment
+fn close(elements: &[Element]) -> Doc {
+    match elements.last() {
+        Some(Element {
+            node: Node::Comment { .. },
+            ..
+        }) => Doc::HardLine,
+        _ => Doc::Concat(Vec::new()),
+    }
+}
+
+impl Node {
+    /// Datums in a quote are data, their lists are never indented as forms
+    fn doc(&self, quoted: bool) -> Doc {
+        match self {
+            Node::Atom(atom) => text(atom),
+            Node::Comment { text: comment, .. } => text(comment),
+            Node::Prefixed(prefix, node) => {
+                Doc::Concat(vec![text(prefix), node.doc(matches!(*prefix, "'" | "`"))])
+            }
+            Node::List(elements) => {
+                let doc = list(elements, quoted);
+                Doc::Align(Box::new(Doc::Group(Box::new(doc))))
+            }
+        }
+    }
+}
+
+fn list(elements: &[Element], quoted: bool) -> Doc {
+    let (head, name) = match elements {
+        [head @ Element {
+            node: Node::Atom(name),
+            ..
+        }, second, ..]
+            if !quoted && is_soft(head, second) =>
+        {
+            (head, name)
+        }
+        _ => return block(elements, quoted),
+    };
+    let args = &elements[1..];
+    let distinguished = BODY_FORMS
+        .iter()
+        .find(|(form, _)| form == name)
+        .map(|(_, count)| match (name.as_str(), &args[0].node) {
+            // Named let
+            ("let", Node::Atom(_)) => 2,
+            _ => *count,
+        });
+    let mut docs = vec![text("("), head.node.doc(quoted)];
+    match distinguished {
+        Some(count) => {
+            // Up to the `count`th datum, with the comments between them
+            let split = match count.checked_sub(1) {
+                Some(last) => args
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, element)| !matches!(element.node, Node::Comment { .. }))
+                    .nth(last)
+                    .map_or(args.len(), |(i, _)| i + 1),
+                None => 0,
+            };
+            let (distinguished, body) = args.split_at(split);
+            if !distinguished.is_empty() {
+                docs.push(text(" "));
+                docs.push(Doc::Group(Box::new(Doc::Align(Box::new(Doc::Concat(
+                    sequence(None, distinguished, &Doc::Line, quoted),
+                ))))));
+            }
+            let prev = distinguished.last().unwrap_or(head);
+            let mut body = sequence(Some(prev), body, &Doc::Line, quoted);
+            body.push(close(args));
+            docs.push(Doc::Nest(2, Box::new(Doc::Concat(body))));
+        }
+        None => {
+            let mut args_docs = sequence(None, args, &Doc::Line, quoted);
+            args_docs.push(close(args));
+            docs.push(text(" "));
+            docs.push(Doc::Align(Box::new(Doc::Concat(args_docs))));
+        }
+    }
+    docs.push(text(")"));
+    Doc::Concat(docs)
+}
+
+/// Every element aligned after the opening parenthesis
+fn block(elements: &[Element], quoted: bool) -> Doc {
+    let mut docs = Vec::new();
+    if let Some(Element {
+        node: Node::Comment { trailing, .. },
+        ..
+    }) = elements.first()
+    {
+        docs.push(if *trailing { text(" ") } else { Doc::HardLine });
+    }
+    // Data fill the lines, forms have a line for each element
+    let soft = if quoted {
+        Doc::Group(Box::new(Doc::Line))
+    } else {
+        Doc::Line
+    };
+    docs.extend(sequence(None, elements, &soft, quoted));
+    docs.push(close(elements));
+    Doc::Concat(vec![
+        text("("),
+        Doc::Align(Box::new(Doc::Concat(docs))),
+        text(")"),
+    ])
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+fn print(doc: &Doc, width: usize) -> String {
+    let mut output = String::new();
+    let mut column = 0;
+    // Indentation is written with the next text, so blank lines have no trailing spaces
+    let mut indentation = None;
+    let mut stack = vec![(0, Mode::Break, doc)];
+    while let Some((indent, mode, doc)) = stack.pop() {
+        match doc {
+            Doc::Text(s) => {
+                if let Some(indent) = indentation.take() {
+                    output.extend(repeat_n(' ', indent));
+                }
+                output.push_str(s);
+                column = match s.rfind('\n') {
+                    Some(newline) => s[newline + 1..].chars().count(),
+                    None => column + s.chars().count(),
+                };
+            }
+            Doc::Line if mode == Mode::Flat => {
+                output.push(' ');
+                column += 1;
+            }
+            Doc::Line | Doc::HardLine => {
+                output.push('\n');
+                indentation = Some(indent);
+                column = indent;
+            }
+            Doc::Nest(more, doc) => stack.push((indent + more, mode, doc)),
+            Doc::Align(doc) => stack.push((column, mode, doc)),
+            Doc::Group(doc) => {
+                let mode =
+                    if mode == Mode::Flat || fits(width as isize - column as isize, doc, &stack) {
+                        Mode::Flat
+                    } else {
+                        Mode::Break
+                    };
+                stack.push((indent, mode, doc));
+            }
+            Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (indent, mode, doc))),
+        }
+    }
+    output
+}
+
+/// Does `doc` fit in `remaining` columns when it is flat, with the rest until the next newline
+fn fits(mut remaining: isize, doc: &Doc, rest: &[(usize, Mode, &Doc)]) -> bool {
+    let mut docs = vec![(Mode::Flat, doc)];
+    let mut rest = rest.iter().rev().map(|(_, mode, doc)| (*mode, *doc));
+    while remaining >= 0 {
+        let (mode, doc) = match docs.pop().or_else(|| rest.next()) {
+            Some(next) => next,
+            None => return true,
+        };
+        match doc {
+            Doc::Text(s) => remaining -= s.chars().count() as isize,
+            Doc::Line if mode == Mode::Flat => remaining -= 1,
+            Doc::Line => return true,
+            Doc::HardLine => return mode == Mode::Break,
+            Doc::Nest(_, doc) | Doc::Align(doc) | Doc::Group(doc) => docs.push((mode, doc)),
+            Doc::Concat(children) => docs.extend(children.iter().rev().map(|doc| (mode, doc))),
+        }
+    }
+    false
+}
@@ -1,45 +1,73 @@
 use crate::*;
 
+/// Identifiers which are lexed as keywords
+pub const KEYWORDS: &[&str] = &["define", "lambda", "if", "set!"];
+/// Chars which could be in an identifier besides letters and digits
+pub(crate) const EXTENDED_IDENTIFIER_CHARS: &str = "!$%&*+-/:<=>?@^_~";
+
 pub(super) mod chumsky {
+    use super::{EXTENDED_IDENTIFIER_CHARS, KEYWORDS};
     use crate::span::*;
     use crate::token::*;
     use chumsky::{prelude::*, text::Character};
-    use num::{traits::Pow, BigInt, Signed, Zero};
-    const EXTENDED_IDENTIFIER_CHARS: &str = "!$%&*+-/:<=>?@^_~";
-    fn keyword(keyword: &'static str) -> impl Parser<char, Token, Error = Simple<char>> {
-        filter(|c: &char| c.to_char().is_ascii_alphabetic() || c.to_char() == '_')
-            .map(Some)
-            .chain::<char, Vec<_>, _>(
-                filter(|c: &char| {
-                    c.to_char().is_ascii_alphanumeric() || c.to_char() == '_' || c.to_char() == '!'
-                })
-                .repeated(),
-            )
-            .collect()
-            .try_map(move |s: String, span| {
-                if s == keyword {
-                    Ok(())
-                } else {
-                    Err(Simple::<char>::expected_input_found(span, None, None))
-                }
-            })
-            .map(move |_| Token::Keyword(keyword))
-            .labelled(keyword)
+    use num::{traits::Pow, BigInt, Signed};
+    fn keyword() -> impl Parser<char, Token, Error = Simple<char>> {
+        // Collect the whole identifier, so `define-values` is not a `define` followed by `-values`
+        filter(|c: &char| {
+            c.to_char().is_ascii_alphanumeric()
+                || c.to_char() == '_'
+                || EXTENDED_IDENTIFIER_CHARS.contains(c.to_char())
+        })
+        .repeated()
+        .at_least(1)
+        .collect()
+        .try_map(move |s: String, span| {
+            KEYWORDS
+                .iter()
+                .find(|keyword| **keyword == s)
+                .map(|keyword| Token::Keyword(keyword))
+                .ok_or_else(|| Simple::<char>::expected_input_found(span, None, None))
+        })
+        .labelled("keyword")
     }
 
     pub fn lexer() -> impl Parser<char, Vec<Spanned<Token>>, Error = Simple<char>> {
+        // A source of only comments has no token to pad
+        atmosphere()
+            .ignore_then(token().repeated())
+            .then_ignore(end())
+    }
+
+    /// Whitespaces and comments
+    pub fn atmosphere() -> impl Parser<char, (), Error = Simple<char>> + Clone {
+        let comment = just(';')
+            .then(take_until(text::newline().or(end())))
+            .ignored()
+            .labelled("comment");
+        comment.padded().repeated().ignored().padded()
+    }
+
+    /// A token with the whitespaces and comments around it
+    pub fn token() -> impl Parser<char, Spanned<Token>, Error = Simple<char>> {
         macro_rules! char {
             ($s: expr) => {
                 just($s).map(|_| Token::Keyword($s)).labelled($s)
             };
         }
 
+        // A dot is a token only if it is followed by a delimiter, so `1.` is not `1` and `.`
+        let dot = char!(".").then_ignore(
+            filter(|c: &char| c.is_whitespace() || *c == '(' || *c == ')' || *c == ';')
+                .ignored()
+                .or(end())
+                .rewind(),
+        );
+        let abbreviation = char!("'").or(char!("`")).or(char!(",@")).or(char!(","));
         let keyword = char!("(")
             .or(char!(")"))
-            .or(keyword("define"))
-            .or(keyword("lambda"))
-            .or(keyword("if"))
-            .or(keyword("set!"));
+            .or(dot)
+            .or(abbreviation)
+            .or(keyword());
         let boolean = choice((
             just("#t").to(Primitive::Bool(true).into()),
             just("#f").to(Primitive::Bool(false).into()),
@@ -51,26 +79,24 @@ pub(super) mod chumsky {
             .labelled("number");
         let real = integer_raw
             .then_ignore(just("."))
-            .then(text::int(10))
+            .then(
+                filter(|c: &char| c.is_ascii_digit())
+                    .repeated()
+                    .at_least(1)
+                    .collect::<String>(),
+            )
             .map(|(int, frac)| {
-                let mut numer: BigInt = int.parse().unwrap();
-                let sign = numer.sign();
-                numer = numer.abs();
-
-                let frac: BigInt = frac.parse().unwrap();
-                let mut denom_log10 = 0u32;
-                {
-                    let mut frac: BigInt = frac.clone();
-                    while !frac.is_zero() {
-                        frac /= 10;
-                        denom_log10 += 1;
-                    }
-                };
-                numer = numer * BigInt::from(10).pow(denom_log10) + frac;
-                numer = match sign {
-                    num::bigint::Sign::Minus => -numer,
-                    _ => numer,
-                };
+                // `-0.5` is negative though its integer part is not
+                let negative = int.starts_with('-');
+                let numer: BigInt = int.parse().unwrap();
+                // Trailing zeros are not significant, leading zeros are
+                let frac = frac.trim_end_matches('0');
+                let denom_log10 = frac.len() as u32;
+                let mut numer = numer.abs() * BigInt::from(10).pow(denom_log10)
+                    + frac.parse::<BigInt>().unwrap_or_default();
+                if negative {
+                    numer = -numer;
+                }
                 Real { numer, denom_log10 }.into()
             });
         let ident =
@@ -80,22 +106,66 @@ pub(super) mod chumsky {
                 .collect()
                 .map(|s| Primitive::Ident(s).into())
                 .labelled("ident");
-        let comment = just(';')
-            .then(take_until(text::newline().or(end())))
-            .ignored()
-            .labelled("comment");
-        let token = keyword
+        // The ellipsis of patterns is the only identifier with a dot
+        let ellipsis = just("...").map(|s: &str| Primitive::Ident(s.into()).into());
+        let escape = just('\\').ignore_then(choice((
+            just('\\'),
+            just('"'),
+            just('|'),
+            just('n').to('\n'),
+            just('t').to('\t'),
+            just('r').to('\r'),
+            just('a').to('\x07'),
+            just('b').to('\x08'),
+            just('0').to('\0'),
+            just('x')
+                .ignore_then(
+                    filter(|c: &char| c.is_ascii_hexdigit())
+                        .repeated()
+                        .at_least(1)
+                        .collect::<String>(),
+                )
+                .then_ignore(just(';'))
+                .try_map(|hex, span| {
+                    u32::from_str_radix(&hex, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .ok_or_else(|| Simple::custom(span, "Invalid hex scalar value"))
+                }),
+        )));
+        let string = just('"')
+            .ignore_then(
+                filter(|c: &char| *c != '\\' && *c != '"')
+                    .or(escape)
+                    .repeated(),
+            )
+            .then_ignore(just('"'))
+            .collect()
+            .map(|s| Primitive::String(s).into())
+            .labelled("string");
+        // `|tmp#1|` is an identifier of any chars, as `write` prints it
+        let bar_ident = just('|')
+            .ignore_then(
+                filter(|c: &char| *c != '\\' && *c != '|')
+                    .or(escape)
+                    .repeated(),
+            )
+            .then_ignore(just('|'))
+            .collect()
+            .map(|s| Primitive::Ident(s).into())
+            .labelled("ident");
+        let token = ellipsis
+            .or(keyword)
+            .or(string)
             .or(boolean)
             .or(real)
             .or(integer)
             .or(ident)
+            .or(bar_ident)
             .labelled("token");
         token
             .map_with_span(|token, span| (token, span))
-            .padded_by(comment.repeated().padded())
-            .padded()
-            .repeated()
-            .then_ignore(end())
+            .padded_by(atmosphere())
     }
 }
 
@@ -106,3 +176,43 @@ pub fn tokenize(source: &str, source_path: &str) -> Result<Vec<Spanned<Token>>,
             .display_every_expected(false)
     })
 }
+
+/// A token and the offset after the whitespaces and comments following it
+pub(crate) type Lexed = (Spanned<Token>, usize);
+
+/// Lex the token at the start of `rest`, which is at the char `offset` of its source, `None` at the
+/// end of input. The chars after the token are not read.
+pub(crate) fn next_token(
+    rest: &str,
+    offset: usize,
+) -> Result<Option<Lexed>, Vec<::chumsky::prelude::Simple<char>>> {
+    use ::chumsky::{prelude::*, Stream};
+    let step = |eoi: usize| {
+        let chars = rest
+            .chars()
+            .enumerate()
+            .map(move |(i, c)| (c, offset + i..offset + i + 1));
+        chumsky::atmosphere()
+            .ignore_then(chumsky::token().map(Some).or(end().to(None)))
+            .map_with_span(|token, span: Span| token.map(|token| (token, span.end)))
+            .parse(Stream::from_iter(eoi..eoi, chars))
+    };
+    // The end of input is only known by reading the rest, which is needed for errors and the end
+    match step(usize::MAX) {
+        Ok(Some(token)) => Ok(Some(token)),
+        _ => step(offset + rest.chars().count()),
+    }
+}
+
+/// Fold the case of identifiers as `#!fold-case` does, the tokens of `include-ci` are folded
+pub fn fold_case(tokens: &mut [Spanned<Token>]) {
+    for (token, _) in tokens {
+        if let Token::Primitive(Primitive::Ident(ident)) = token {
+            let folded = ident.to_lowercase();
+            *token = match KEYWORDS.iter().find(|keyword| **keyword == folded) {
+                Some(keyword) => Token::Keyword(keyword),
+                None => Primitive::Ident(folded).into(),
+            };
+        }
+    }
+}
@@ -1,15 +1,30 @@
 mod error;
 pub use error::*;
+mod format;
+pub use format::*;
 pub mod ast;
 pub use ast::*;
 mod parser;
 pub use parser::*;
+mod reader;
+pub use reader::*;
 pub mod token;
 pub use token::*;
 mod lexer;
 pub use lexer::*;
+pub mod lint;
+mod node;
+pub use node::*;
 mod span;
 pub use span::*;
+mod structure;
+pub use structure::*;
+mod unparse;
+pub use unparse::*;
+pub mod analysis;
+mod loader;
+pub mod visit;
+pub use loader::*;
 mod datum;
 pub mod transformer;
 pub use datum::*;
@@ -0,0 +1,228 @@
+//! Lints over the resolved AST.
+//!
+//! A [`Lint`] reports [`Diagnostic`]s of a program, the [`Linter`] runs the lints and sets the
+//! severity of each code. [`LintReport`] renders the diagnostics as [`ParseError`] renders errors.
+
+pub mod builtin;
+
+use crate::analysis::scope::Scopes;
+use crate::error::{locate, with_backtrace, write_report};
+use crate::*;
+use ariadne::{Color, Fmt, Label, Report, ReportKind};
+use std::collections::HashMap;
+
+/// How a diagnostic is reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Severity {
+    /// Not reported
+    Allow,
+    Warning,
+    Error,
+}
+
+/// A problem found by a lint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Code of the lint which reports it, such as `unused-variable`
+    pub code: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    pub span: Span,
+    /// Related spans, such as the definition of a procedure
+    pub labels: Vec<Spanned<String>>,
+}
+
+impl Diagnostic {
+    /// The code and the severity are set by the [`Linter`]
+    pub fn new(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            code: "",
+            severity: Severity::Warning,
+            message: message.into(),
+            span,
+            labels: Vec::new(),
+        }
+    }
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push((message.into(), span));
+        self
+    }
+}
+
+/// What the lints could look at
+pub struct LintContext<'ast> {
+    pub program: &'ast Program,
+    pub scopes: Scopes,
+    pub nodes: NodeIndex<'ast>,
+}
+
+impl<'ast> LintContext<'ast> {
+    pub fn new(program: &'ast Program) -> Self {
+        let nodes = NodeIndex::new(program);
+        Self {
+            program,
+            scopes: Scopes::indexed(program, &nodes),
+            nodes,
+        }
+    }
+}
+
+pub trait Lint {
+    /// Unique name of the lint in kebab case
+    fn code(&self) -> &'static str;
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+    fn check(&self, context: &LintContext) -> Vec<Diagnostic>;
+}
+
+/// Runs lints over programs
+pub struct Linter {
+    lints: Vec<Box<dyn Lint>>,
+    severities: HashMap<&'static str, Severity>,
+}
+
+impl Default for Linter {
+    /// With the builtin lints
+    fn default() -> Self {
+        builtin::LINTS
+            .iter()
+            .fold(Self::empty(), |linter, lint| linter.with_boxed_lint(lint()))
+    }
+}
+
+impl Linter {
+    /// With the builtin lints
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Without any lint
+    pub fn empty() -> Self {
+        Self {
+            lints: Vec::new(),
+            severities: HashMap::new(),
+        }
+    }
+    /// Add a lint, which replaces the one of the same code
+    pub fn with_lint(self, lint: impl Lint + 'static) -> Self {
+        self.with_boxed_lint(Box::new(lint))
+    }
+    fn with_boxed_lint(mut self, lint: Box<dyn Lint>) -> Self {
+        self.lints
+            .retain(|registered| registered.code() != lint.code());
+        self.severities.insert(lint.code(), lint.default_severity());
+        self.lints.push(lint);
+        self
+    }
+    /// Report the lint of `code` as `severity`, ignored if there is no such lint
+    pub fn with_severity(mut self, code: &str, severity: Severity) -> Self {
+        if let Some(current) = self.severities.get_mut(code) {
+            *current = severity;
+        }
+        self
+    }
+    /// Codes of the lints, in the order they run
+    pub fn codes(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.lints.iter().map(|lint| lint.code())
+    }
+    /// Diagnostics of the lints which are not allowed, in the order of their spans
+    pub fn check(&self, program: &Program) -> Vec<Diagnostic> {
+        let context = LintContext::new(program);
+        let mut diagnostics = Vec::new();
+        for lint in &self.lints {
+            let code = lint.code();
+            let severity = self.severities[code];
+            if severity == Severity::Allow {
+                continue;
+            }
+            diagnostics.extend(
+                lint.check(&context)
+                    .into_iter()
+                    .map(|diagnostic| Diagnostic {
+                        code,
+                        severity,
+                        ..diagnostic
+                    }),
+            );
+        }
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+        diagnostics
+    }
+}
+
+/// Renders diagnostics with the lines of the sources
+#[derive(Debug)]
+pub struct LintReport {
+    diagnostics: Vec<Diagnostic>,
+    sources: SourceMap,
+    expansions: Expansions,
+    colorful: bool,
+}
+
+impl LintReport {
+    /// `sources` are what the program is parsed from, see [`Parser::sources`]
+    pub fn new(diagnostics: Vec<Diagnostic>, sources: SourceMap) -> Self {
+        Self {
+            diagnostics,
+            sources,
+            expansions: Expansions::default(),
+            colorful: true,
+        }
+    }
+    /// Point out the transformer uses which produced a diagnostic
+    pub fn with_expansions(self, expansions: Expansions) -> Self {
+        Self { expansions, ..self }
+    }
+    /// Should display with color or not, default: true
+    pub fn with_color(self, colorful: bool) -> Self {
+        Self { colorful, ..self }
+    }
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+    /// Is any diagnostic an error
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+}
+
+impl std::fmt::Display for LintReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = |span: &Span, message: &str, color| {
+            let label = Label::new(locate(&self.sources, span.clone()));
+            if self.colorful {
+                label.with_message(message.fg(color)).with_color(color)
+            } else {
+                label.with_message(message)
+            }
+        };
+        for diagnostic in &self.diagnostics {
+            let (kind, color) = match diagnostic.severity {
+                Severity::Error => (ReportKind::Error, Color::Red),
+                _ => (ReportKind::Warning, Color::Yellow),
+            };
+            let (path, span) = locate(&self.sources, diagnostic.span.clone());
+            let report = Report::build(kind, path, span.start)
+                .with_code(diagnostic.code)
+                .with_message(&diagnostic.message)
+                .with_label(label(&diagnostic.span, &diagnostic.message, color));
+            let report = diagnostic
+                .labels
+                .iter()
+                .fold(report, |report, (message, span)| {
+                    report.with_label(label(span, message, Color::Cyan))
+                });
+            let report = with_backtrace(
+                report,
+                &diagnostic.span,
+                &self.expansions,
+                &self.sources,
+                self.colorful,
+            );
+            write_report(report, &self.sources, self.colorful, f)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,461 @@
+use super::*;
+use crate::analysis::scope::{Binding, BindingKind, Resolution};
+use crate::visit::*;
+
+/// Constructors of the lints which a [`Linter`] runs by default
+pub const LINTS: &[fn() -> Box<dyn Lint>] = &[
+    || Box::new(UnusedVariable),
+    || Box::new(UnusedParameter),
+    || Box::new(ShadowedBinding),
+    || Box::new(UnboundAssignment),
+    || Box::new(DuplicateParameter),
+    || Box::new(DuplicateDefinition),
+    || Box::new(IfWithoutAlternative),
+    || Box::new(ArityMismatch),
+    || Box::new(NonTailRecursion),
+];
+
+/// Bindings of `kind` which are never read, unless their names start with `_`
+fn unused<'a>(
+    context: &'a LintContext,
+    kind: BindingKind,
+) -> impl Iterator<Item = &'a Binding> + 'a {
+    context
+        .scopes
+        .bindings
+        .iter()
+        .enumerate()
+        .filter(move |(index, binding)| {
+            binding.kind == kind
+                && !binding.name.0.starts_with('_')
+                && context
+                    .scopes
+                    .references_to(*index)
+                    .all(|reference| reference.assignment)
+        })
+        .map(|(_, binding)| binding)
+}
+
+/// A definition in a procedure body which is never read
+pub struct UnusedVariable;
+
+impl Lint for UnusedVariable {
+    fn code(&self) -> &'static str {
+        "unused-variable"
+    }
+    fn check(&self, context: &LintContext) -> Vec<Diagnostic> {
+        unused(context, BindingKind::Internal)
+            .map(|Binding { name, .. }| {
+                Diagnostic::new(name.1.clone(), format!("Unused variable `{}`", name.0))
+            })
+            .collect()
+    }
+}
+
+/// A formal parameter or a `guard` variable which is never read
+pub struct UnusedParameter;
+
+impl Lint for UnusedParameter {
+    fn code(&self) -> &'static str {
+        "unused-parameter"
+    }
+    fn check(&self, context: &LintContext) -> Vec<Diagnostic> {
+        unused(context, BindingKind::Parameter)
+            .map(|Binding { name, .. }| {
+                Diagnostic::new(name.1.clone(), format!("Unused parameter `{}`", name.0))
+            })
+            .collect()
+    }
+}
+
+/// A binding which hides a binding of the same name in an enclosing scope
+pub struct ShadowedBinding;
+
+impl Lint for ShadowedBinding {
+    fn code(&self) -> &'static str {
+        "shadowed-binding"
+    }
+    fn check(&self, context: &LintContext) -> Vec<Diagnostic> {
+        let bindings = &context.scopes.bindings;
+        bindings
+            .iter()
+            .filter_map(|binding| {
+                let shadowed = &bindings[binding.shadows?];
+                Some(
+                    Diagnostic::new(
+                        binding.name.1.clone(),
+                        format!("`{}` shadows an outer binding", binding.name.0),
+                    )
+                    .with_label(shadowed.name.1.clone(), "Shadowed binding"),
+                )
+            })
+            .collect()
+    }
+}
+
+/// `set!` on a name which is not bound in the program
+pub struct UnboundAssignment;
+
+impl Lint for UnboundAssignment {
+    fn code(&self) -> &'static str {
+        "unbound-assignment"
+    }
+    fn check(&self, context: &LintContext) -> Vec<Diagnostic> {
+        context
+            .scopes
+            .references
+            .iter()
+            .filter(|reference| reference.assignment && reference.resolution == Resolution::Free)
+            .map(|reference| {
+                Diagnostic::new(
+                    reference.name.1.clone(),
+                    format!("`set!` on unbound variable `{}`", reference.name.0),
+                )
+            })
+            .collect()
+    }
+}
+
+/// A name which appears twice in the formals of `lambda`, `define-values`, `let-values` or
+/// `receive`
+pub struct DuplicateParameter;
+
+impl Lint for DuplicateParameter {
+    fn code(&self) -> &'static str {
+        "duplicate-parameter"
+    }
+    fn check(&self, context: &LintContext) -> Vec<Diagnostic> {
+        #[derive(Default)]
+        struct Duplicates(Vec<Diagnostic>);
+        impl Duplicates {
+            fn check<'a>(&mut self, names: impl IntoIterator<Item = &'a Spanned<String>>) {
+                let mut seen: HashMap<&str, &Span> = HashMap::new();
+                for (name, span) in names {
+                    if let Some(first) = seen.insert(name, span) {
+                        self.0.push(
+                            Diagnostic::new(
+                                span.clone(),
+                                format!("Duplicate parameter `{}`", name),
+                            )
+                            .with_label(first.clone(), "First bound here"),
+                        );
+                        seen.insert(name, first);
+                    }
+                }
+            }
+        }
+        impl<'ast> Visitor<'ast> for Duplicates {
+            fn visit_expression(&mut self, expr: &'ast Spanned<Expression>) {
+                if let Expression::Procedure { args, rest, .. } = &expr.0 {
+                    self.check(args.iter().chain(rest));
+                }
+                walk_expression(self, expr);
+            }
+            fn visit_formals(&mut self, formals: &'ast Spanned<Formals>) {
+                self.check(formals.0.args.iter().chain(&formals.0.rest));
+            }
+        }
+        let mut duplicates = Duplicates::default();
+        duplicates.visit_program(context.program);
+        duplicates.0
+    }
+}
+
+/// A name defined twice at the top level of the program or a library
+pub struct DuplicateDefinition;
+
+impl Lint for DuplicateDefinition {
+    fn code(&self) -> &'static str {
+        "duplicate-definition"
+    }
+    fn check(&self, context: &LintContext) -> Vec<Diagnostic> {
+        let mut seen: HashMap<(usize, &str), &Span> = HashMap::new();
+        let mut diagnostics = Vec::new();
+        for binding in &context.scopes.bindings {
+            if binding.kind != BindingKind::TopLevel {
+                continue;
+            }
+            let (name, span) = &binding.name;
+            match seen.get(&(binding.scope, name.as_str())) {
+                Some(first) => diagnostics.push(
+                    Diagnostic::new(span.clone(), format!("`{}` is defined again", name))
+                        .with_label((*first).clone(), "First defined here"),
+                ),
+                None => {
+                    seen.insert((binding.scope, name), span);
+                }
+            }
+        }
+        diagnostics
+    }
+}
+
+/// `(if test conseq)` whose value is used, which is unspecified if `test` is false
+pub struct IfWithoutAlternative;
+
+impl Lint for IfWithoutAlternative {
+    fn code(&self) -> &'static str {
+        "if-without-alternative"
+    }
+    fn check(&self, context: &LintContext) -> Vec<Diagnostic> {
+        let mut values = Values {
+            used: false,
+            diagnostics: Vec::new(),
+        };
+        values.visit_program(context.program);
+        values.diagnostics
+    }
+}
+
+/// Tracks whether the value of the expression being visited is used. The value of a procedure
+/// body is returned to the caller, which might ignore it, so it is not considered to be used.
+struct Values {
+    used: bool,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Values {
+    fn visit(&mut self, expr: &Spanned<Expression>, used: bool) {
+        let outer = std::mem::replace(&mut self.used, used);
+        self.visit_expression(expr);
+        self.used = outer;
+    }
+    /// Only the value of the last expression is the value of the sequence
+    fn sequence<'ast>(&mut self, exprs: impl IntoIterator<Item = &'ast Spanned<Expression>>) {
+        let used = self.used;
+        let mut exprs = exprs.into_iter().peekable();
+        while let Some(expr) = exprs.next() {
+            self.visit(expr, used && exprs.peek().is_none());
+        }
+    }
+}
+
+impl<'ast> Visitor<'ast> for Values {
+    fn visit_body(&mut self, body: &'ast ProcedureBody) {
+        for def in &body.defs {
+            self.visit_definition(def);
+        }
+        self.sequence(body.exprs.iter().chain(Some(&body.last_expr)));
+    }
+    fn visit_definition(&mut self, def: &'ast Spanned<Definition>) {
+        let outer = std::mem::replace(&mut self.used, true);
+        walk_definition(self, def);
+        self.used = outer;
+    }
+    fn visit_cond_clause(&mut self, (clause, _): &'ast Spanned<CondClause>) {
+        match clause {
+            CondClause::Test { test, body } => {
+                self.visit(test, true);
+                self.sequence(body);
+            }
+            CondClause::Arrow { test, receiver } => {
+                self.visit(test, true);
+                self.visit(receiver, true);
+            }
+            CondClause::Else(body) => self.sequence(body),
+        }
+    }
+    fn visit_expression(&mut self, expr: &'ast Spanned<Expression>) {
+        match &expr.0 {
+            Expression::Conditional {
+                test,
+                conseq,
+                alter,
+            } => {
+                if alter.is_none() && self.used {
+                    self.diagnostics.push(Diagnostic::new(
+                        expr.1.clone(),
+                        "The value of `if` without an alternative is used",
+                    ));
+                }
+                self.visit(test, true);
+                self.visit_expression(conseq);
+                if let Some(alter) = alter {
+                    self.visit_expression(alter);
+                }
+            }
+            Expression::Procedure { body, .. } => {
+                let outer = std::mem::replace(&mut self.used, false);
+                self.visit_body(body);
+                self.used = outer;
+            }
+            Expression::Begin { defs, exprs } => {
+                for def in defs {
+                    self.visit_definition(def);
+                }
+                self.sequence(exprs);
+            }
+            Expression::LetValues { bindings, body, .. } => {
+                for binding in bindings {
+                    self.visit(&binding.0 .1, true);
+                }
+                self.visit_body(body);
+            }
+            Expression::Receive { expr, body, .. } => {
+                self.visit(expr, true);
+                self.visit_body(body);
+            }
+            Expression::Guard { clauses, body, .. } => {
+                for clause in clauses {
+                    self.visit_cond_clause(clause);
+                }
+                self.visit_body(body);
+            }
+            Expression::ProcedureCall { .. }
+            | Expression::Assignment(..)
+            | Expression::Primitive(_)
+            | Expression::Quote(_)
+            | Expression::Error => {
+                let outer = std::mem::replace(&mut self.used, true);
+                walk_expression(self, expr);
+                self.used = outer;
+            }
+        }
+    }
+}
+
+/// A call to a defined procedure with a wrong count of arguments. Procedures which are assigned by
+/// `set!` are not checked, since they might be replaced.
+pub struct ArityMismatch;
+
+/// `n arguments`, or `at least n arguments` if there is a rest parameter
+fn arity(count: usize, rest: bool) -> String {
+    format!(
+        "{}{} argument{}",
+        if rest { "at least " } else { "" },
+        count,
+        if count == 1 { "" } else { "s" }
+    )
+}
+
+impl Lint for ArityMismatch {
+    fn code(&self) -> &'static str {
+        "arity-mismatch"
+    }
+    fn default_severity(&self) -> Severity {
+        Severity::Error
+    }
+    fn check(&self, context: &LintContext) -> Vec<Diagnostic> {
+        struct Calls<'a, 'ast> {
+            nodes: &'a NodeIndex<'ast>,
+            /// Count of parameters and whether there is a rest parameter, by the definition
+            procedures: HashMap<NodeId, (usize, bool)>,
+            /// Spans of the calls, their operators and the counts of arguments
+            calls: Vec<(&'ast Span, &'ast Spanned<Expression>, usize)>,
+        }
+        impl<'ast> Visitor<'ast> for Calls<'_, 'ast> {
+            fn visit_definition(&mut self, def: &'ast Spanned<Definition>) {
+                if let Definition::Variable(_, (Expression::Procedure { args, rest, .. }, _)) =
+                    &def.0
+                {
+                    let id = self.nodes.id(def).unwrap();
+                    self.procedures.insert(id, (args.len(), rest.is_some()));
+                }
+                walk_definition(self, def);
+            }
+            fn visit_expression(&mut self, expr: &'ast Spanned<Expression>) {
+                if let Expression::ProcedureCall { operator, args } = &expr.0 {
+                    if let Expression::Primitive(Primitive::Ident(_)) = &operator.0 {
+                        self.calls.push((&expr.1, operator, args.len()));
+                    }
+                }
+                walk_expression(self, expr);
+            }
+        }
+        let LintContext { scopes, nodes, .. } = context;
+        let mut calls = Calls {
+            nodes,
+            procedures: HashMap::new(),
+            calls: Vec::new(),
+        };
+        calls.visit_program(context.program);
+        calls
+            .calls
+            .iter()
+            .filter_map(|(call, operator, count)| {
+                let Some(Resolution::Bound(index)) = scopes.resolve(nodes.id(*operator)?) else {
+                    return None;
+                };
+                let binding = &scopes.bindings[index];
+                let (name, span) = &binding.name;
+                let (params, rest) = *calls.procedures.get(&binding.node)?;
+                let accepted = if rest {
+                    *count >= params
+                } else {
+                    *count == params
+                };
+                if accepted || scopes.references_to(index).any(|r| r.assignment) {
+                    return None;
+                }
+                Some(
+                    Diagnostic::new(
+                        (*call).clone(),
+                        format!(
+                            "`{}` takes {} but {} {} given",
+                            name,
+                            arity(params, rest),
+                            count,
+                            if *count == 1 { "is" } else { "are" }
+                        ),
+                    )
+                    .with_label(span.clone(), format!("`{}` is defined here", name)),
+                )
+            })
+            .collect()
+    }
+}
+
+/// A call of a procedure to itself which is not in tail position, so each recursion takes stack
+/// space. Calls from procedures nested in it are not checked. Allowed by default.
+pub struct NonTailRecursion;
+
+impl Lint for NonTailRecursion {
+    fn code(&self) -> &'static str {
+        "non-tail-recursion"
+    }
+    fn default_severity(&self) -> Severity {
+        Severity::Allow
+    }
+    fn check(&self, context: &LintContext) -> Vec<Diagnostic> {
+        let LintContext { scopes, nodes, .. } = context;
+        (0..nodes.len())
+            .map(NodeId)
+            .filter_map(|id| {
+                let Node::Expression((Expression::ProcedureCall { operator, .. }, call)) =
+                    nodes.node(id)
+                else {
+                    return None;
+                };
+                if nodes.is_tail(id) {
+                    return None;
+                }
+                let Some(Resolution::Bound(index)) = scopes.resolve(nodes.id(operator.as_ref())?)
+                else {
+                    return None;
+                };
+                let procedure = nodes.ancestors(id).find(|ancestor| {
+                    matches!(
+                        nodes.node(*ancestor),
+                        Node::Expression((Expression::Procedure { .. }, _))
+                    )
+                })?;
+                let definition = nodes.parent(procedure)?;
+                if definition != scopes.bindings[index].node {
+                    return None;
+                }
+                let Node::Definition((Definition::Variable((name, span), _), _)) =
+                    nodes.node(definition)
+                else {
+                    return None;
+                };
+                Some(
+                    Diagnostic::new(
+                        call.clone(),
+                        format!("Recursive call to `{}` is not in tail position", name),
+                    )
+                    .with_label(span.clone(), format!("`{}` is defined here", name)),
+                )
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,76 @@
+use std::{
+    collections::HashMap,
+    io,
+    path::{Component, Path, PathBuf},
+};
+
+/// Where the files of `include` and `include-ci` come from
+pub trait SourceLoader {
+    /// Resolve `path` which is written in the source `from`, by default relative to the directory
+    /// of `from`
+    fn resolve(&self, from: &str, path: &str) -> String {
+        let joined = match Path::new(from).parent() {
+            Some(dir) => dir.join(path),
+            None => PathBuf::from(path),
+        };
+        normalize(&joined)
+    }
+    fn load(&self, path: &str) -> io::Result<String>;
+}
+
+/// Remove `.` and `..` lexically, so the same file is always resolved to the same path
+fn normalize(path: &Path) -> String {
+    let mut components: Vec<Component> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir if matches!(components.last(), Some(Component::Normal(_))) => {
+                components.pop();
+            }
+            component => components.push(component),
+        }
+    }
+    components
+        .iter()
+        .collect::<PathBuf>()
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// Load sources from the file system
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FsLoader;
+
+impl SourceLoader for FsLoader {
+    fn load(&self, path: &str) -> io::Result<String> {
+        Ok(std::fs::read_to_string(path)?.replace("\r\n", "\n"))
+    }
+}
+
+/// Load sources from memory, useful for tests
+#[derive(Debug, Default, Clone)]
+pub struct MemoryLoader {
+    files: HashMap<String, String>,
+}
+
+impl MemoryLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_file(mut self, path: &str, source: &str) -> Self {
+        self.insert(path, source);
+        self
+    }
+    pub fn insert(&mut self, path: &str, source: &str) {
+        self.files.insert(path.to_owned(), source.to_owned());
+    }
+}
+
+impl SourceLoader for MemoryLoader {
+    fn load(&self, path: &str) -> io::Result<String> {
+        self.files
+            .get(path)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No such file in memory"))
+    }
+}
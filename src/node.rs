@@ -0,0 +1,197 @@
+use crate::visit::*;
+use crate::*;
+use std::collections::{HashMap, HashSet};
+
+/// The `NodeIndex` given with a program was built for another program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ForeignIndex;
+
+impl std::fmt::Display for ForeignIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The node index was built for another program")
+    }
+}
+impl std::error::Error for ForeignIndex {}
+
+/// Identifies a definition or an expression of a program, unlike spans it is unique.
+///
+/// Nodes are numbered in the order they are written, so the same program always gets the same
+/// ids, even if it is cloned or parsed again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(pub usize);
+
+/// A node of the AST
+#[derive(Debug, Clone, Copy)]
+pub enum Node<'ast> {
+    Definition(&'ast Spanned<Definition>),
+    Expression(&'ast Spanned<Expression>),
+}
+
+impl<'ast> Node<'ast> {
+    pub fn span(&self) -> &'ast Span {
+        match self {
+            Node::Definition((_, span)) | Node::Expression((_, span)) => span,
+        }
+    }
+    /// Identifies the node by its address, since equal nodes could be at different places
+    fn key(&self) -> (bool, *const ()) {
+        match self {
+            Node::Definition(def) => (false, *def as *const _ as *const ()),
+            Node::Expression(expr) => (true, *expr as *const _ as *const ()),
+        }
+    }
+}
+
+impl<'ast> From<&'ast Spanned<Definition>> for Node<'ast> {
+    fn from(def: &'ast Spanned<Definition>) -> Self {
+        Node::Definition(def)
+    }
+}
+
+impl<'ast> From<&'ast Spanned<Expression>> for Node<'ast> {
+    fn from(expr: &'ast Spanned<Expression>) -> Self {
+        Node::Expression(expr)
+    }
+}
+
+#[derive(Debug)]
+struct Entry<'ast> {
+    node: Node<'ast>,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    tail: bool,
+}
+
+/// Ids of the nodes of a program, with their parents and children.
+///
+/// Nodes in libraries, import sets and cond clauses are children of the innermost definition or
+/// expression containing them.
+///
+/// Expressions in tail position are marked as R7RS section 3.5 describes: the last expression of a
+/// procedure body is in tail position, and so are the branches of an `if`, and the last
+/// expressions of a `begin` and of the bodies of `let-values` and `receive`, if they are in tail
+/// position themselves. Derived forms such as `cond` or `let` are expanded into these, and the body
+/// of a `guard` is not in tail position.
+///
+/// The index is tied to the borrow of one program, it finds the ids of nodes by their addresses.
+/// Nodes of a clone, a fold or another parse of the program have no ids in it, index them anew.
+#[derive(Debug, Default)]
+pub struct NodeIndex<'ast> {
+    /// The indexed program
+    program: Option<&'ast Program>,
+    nodes: Vec<Entry<'ast>>,
+    /// Nodes which have no parent
+    roots: Vec<NodeId>,
+    ids: HashMap<(bool, *const ()), NodeId>,
+    /// Ancestors of the node being visited
+    stack: Vec<NodeId>,
+    /// Expressions in tail position which are not entered yet
+    tails: HashSet<(bool, *const ())>,
+}
+
+impl<'ast> NodeIndex<'ast> {
+    pub fn new(program: &'ast Program) -> Self {
+        let mut index = Self {
+            program: Some(program),
+            ..Self::default()
+        };
+        index.visit_program(program);
+        index
+    }
+    /// Is this the index of `program`, rather than of a clone or another program
+    pub fn indexes(&self, program: &Program) -> bool {
+        self.program
+            .is_some_and(|indexed| std::ptr::eq(indexed, program))
+    }
+    /// Count of the nodes, ids are less than it
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+    /// Id of `node`, which must be borrowed from the indexed program
+    pub fn id(&self, node: impl Into<Node<'ast>>) -> Option<NodeId> {
+        self.ids.get(&node.into().key()).copied()
+    }
+    pub fn node(&self, id: NodeId) -> Node<'ast> {
+        self.nodes[id.0].node
+    }
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].parent
+    }
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        &self.nodes[id.0].children
+    }
+    /// Is the node an expression in tail position of its innermost procedure
+    pub fn is_tail(&self, id: NodeId) -> bool {
+        self.nodes[id.0].tail
+    }
+    /// Parent, grandparent and so on
+    pub fn ancestors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(self.parent(id), |id| self.parent(*id))
+    }
+    /// The innermost node whose span contains `offset`, in chars as spans are. The first one is
+    /// chosen if sibling nodes overlap.
+    pub fn node_at(&self, offset: usize) -> Option<NodeId> {
+        let contains = |id: &&NodeId| self.node(**id).span().contains(&offset);
+        let mut innermost = *self.roots.iter().find(contains)?;
+        while let Some(child) = self.children(innermost).iter().find(contains) {
+            innermost = *child;
+        }
+        Some(innermost)
+    }
+
+    fn enter(&mut self, node: Node<'ast>) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        let parent = self.stack.last().copied();
+        match parent {
+            Some(parent) => self.nodes[parent.0].children.push(id),
+            None => self.roots.push(id),
+        }
+        self.nodes.push(Entry {
+            node,
+            parent,
+            children: Vec::new(),
+            tail: self.tails.remove(&node.key()),
+        });
+        self.ids.insert(node.key(), id);
+        self.stack.push(id);
+        id
+    }
+    fn tail(&mut self, expr: &'ast Spanned<Expression>) {
+        self.tails.insert(Node::from(expr).key());
+    }
+}
+
+impl<'ast> Visitor<'ast> for NodeIndex<'ast> {
+    fn visit_definition(&mut self, def: &'ast Spanned<Definition>) {
+        self.enter(def.into());
+        walk_definition(self, def);
+        self.stack.pop();
+    }
+    fn visit_expression(&mut self, expr: &'ast Spanned<Expression>) {
+        let id = self.enter(expr.into());
+        let tail = self.is_tail(id);
+        match &expr.0 {
+            Expression::Procedure { body, .. } => self.tail(&body.last_expr),
+            Expression::Conditional { conseq, alter, .. } if tail => {
+                self.tail(conseq);
+                if let Some(alter) = alter {
+                    self.tail(alter);
+                }
+            }
+            Expression::LetValues { body, .. } | Expression::Receive { body, .. } if tail => {
+                self.tail(&body.last_expr)
+            }
+            Expression::Begin { exprs, .. } if tail => {
+                if let Some(last) = exprs.last() {
+                    self.tail(last);
+                }
+            }
+            _ => {}
+        }
+        walk_expression(self, expr);
+        self.stack.pop();
+    }
+}
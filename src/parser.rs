@@ -1,186 +1,185 @@
+use ::chumsky::prelude::Simple;
 use derive_more::{Display, From};
 
-use crate::{transformer::*, *};
+use crate::{
+    transformer::{cond_expand::Features, *},
+    *,
+};
 
-pub(super) mod chumsky {
-    use chumsky::{combinator::MapWithSpan, prelude::*};
-    use std::hash::Hash;
-    use std::ops::Range;
+mod convert;
+pub use convert::{convert, convert_expression};
 
-    use crate::ast::*;
-    use crate::token::*;
-    fn enclosed<T>(
-        parser: impl chumsky::Parser<Token, T, Error = Simple<Token>>,
-    ) -> impl chumsky::Parser<Token, T, Error = Simple<Token>> {
-        parser.delimited_by(just(Token::Keyword("(")), just(Token::Keyword(")")))
-    }
+/// Builtin expansions, which run before the registered transformers, in this order by default
+pub const BUILTINS: &[&str] = &["cond-expand", "include"];
 
-    fn spanned<S: Clone + Hash + Eq, T, I: chumsky::Parser<S, T, Error = Simple<S>>>(
-        parser: I,
-    ) -> MapWithSpan<I, impl Fn(T, Range<usize>) -> (T, Range<usize>) + Clone + Copy, T> {
-        parser.map_with_span(|t, span| (t, span))
-    }
+pub struct Parser {
+    transformers: Vec<(String, Box<dyn Transformer>)>,
+    builtins: Vec<&'static str>,
+    loader: Box<dyn SourceLoader>,
+    features: Features,
+    expansion_limit: usize,
+    sources: SourceMap,
+    expansions: Expansions,
+    warnings: Vec<Simple<Token>>,
+}
 
-    macro_rules! select {
-        ($category: literal, {$($p:pat $(if $guard:expr)? => $out:expr),+ $(,)?}) => ({
-            filter_map(move |span: std::ops::Range<usize>, x| match x {
-                $($p $(if $guard)? => ::core::result::Result::Ok($out)),+,
-                other => Err(Simple::expected_input_found(
-                    span,
-                    Some(Some(Token::Keyword($category))),
-                    Some(other),
-                )),
-            })
-        });
+impl Default for Parser {
+    fn default() -> Self {
+        Parser::builder().build()
     }
+}
 
-    pub fn parser() -> impl chumsky::Parser<Token, Program, Error = Simple<Token>> {
-        macro_rules! map_err_category {
-            ($category: literal, $parser: expr) => {
-                $parser.map_err(|e| {
-                    if let chumsky::error::SimpleReason::Unexpected = e.reason() {
-                        let r = Simple::expected_input_found(
-                            e.span(),
-                            Some(Some(Token::Keyword($category))),
-                            e.found().cloned(),
-                        );
-                        if let Some(label) = e.label() {
-                            r.with_label(label)
-                        } else {
-                            r
-                        }
-                    } else {
-                        e
-                    }
-                })
-            };
-        }
-
-        let ident = spanned(select! {
-            "<identifier>",
-            { Token::Primitive(Primitive::Ident(v)) => v }
-        });
-
-        let proc_body = recursive(|proc_body| {
-            let expr = recursive(|expr| {
-                let primitive = select! {
-                    "<primitive>",
-                    {
-                        Token::Primitive(p) => Expression::Primitive(p),
-                    }
-                };
-                let formals = enclosed(ident.repeated()).or(ident.map(|ident| vec![ident]));
-
-                let lambda = enclosed(
-                    just(Token::Keyword("lambda"))
-                        .ignore_then(formals)
-                        .then(proc_body.clone().map(Box::new))
-                        .map(|(args, body)| Expression::Procedure { args, body }),
-                )
-                .labelled("lambda");
+pub struct ParserBuilder {
+    transformers: Vec<(String, Box<dyn Transformer>)>,
+    builtins: Vec<&'static str>,
+    loader: Box<dyn SourceLoader>,
+    features: Features,
+    expansion_limit: usize,
+}
 
-                let if_expr = enclosed(
-                    just(Token::Keyword("if"))
-                        .ignore_then(expr.clone())
-                        .then(expr.clone())
-                        .then(expr.clone().or_not()),
-                )
-                .map(|((test, conseq), alter)| Expression::Conditional {
-                    test: Box::new(test),
-                    conseq: Box::new(conseq),
-                    alter: alter.map(Box::new),
-                });
+impl Default for ParserBuilder {
+    fn default() -> Self {
+        Self {
+            transformers: Vec::new(),
+            builtins: BUILTINS.to_vec(),
+            loader: Box::new(FsLoader),
+            features: Features::default(),
+            expansion_limit: DEFAULT_EXPANSION_LIMIT,
+        }
+    }
+}
 
-                let proc_call = enclosed(expr.clone().then(expr.clone().repeated()).map(
-                    |(operator, args)| Expression::ProcedureCall {
-                        operator: Box::new(operator),
-                        args,
-                    },
-                ))
-                .labelled("procedure call");
+impl ParserBuilder {
+    /// Transformers are tried in the order they are registered. Registering a name again replaces
+    /// the transformer, keeping its place in the order.
+    pub fn with_transformer(
+        mut self,
+        name: impl Into<String>,
+        transformer: impl Transformer + 'static,
+    ) -> Self {
+        let name = name.into();
+        match self.position(&name) {
+            Some(i) => self.transformers[i].1 = Box::new(transformer),
+            None => self.transformers.push((name, Box::new(transformer))),
+        }
+        self
+    }
+    /// Register a transformer to be tried before the one named `before`, or last if there is no
+    /// such transformer
+    pub fn with_transformer_before(
+        self,
+        before: &str,
+        name: impl Into<String>,
+        transformer: impl Transformer + 'static,
+    ) -> Self {
+        let name = name.into();
+        let mut builder = self.without_transformer(&name);
+        let i = builder
+            .position(before)
+            .unwrap_or(builder.transformers.len());
+        builder
+            .transformers
+            .insert(i, (name, Box::new(transformer)));
+        builder
+    }
+    pub fn without_transformer(mut self, name: &str) -> Self {
+        self.transformers
+            .retain(|(registered, _)| registered != name);
+        self
+    }
+    /// Disable one of `BUILTINS`. Other names are an error, such as `begin`, which is parsed
+    /// rather than expanded.
+    pub fn without_builtin(mut self, name: &str) -> Result<Self, UnknownBuiltin> {
+        let builtin = builtin(name)?;
+        self.builtins.retain(|enabled| *enabled != builtin);
+        Ok(self)
+    }
+    /// Enable only the builtins in `names`, which run in this order. Repeated names are ignored,
+    /// names which are not in `BUILTINS` are an error.
+    pub fn with_builtins(mut self, names: &[&str]) -> Result<Self, UnknownBuiltin> {
+        self.builtins.clear();
+        for name in names {
+            let builtin = builtin(name)?;
+            if !self.builtins.contains(&builtin) {
+                self.builtins.push(builtin);
+            }
+        }
+        Ok(self)
+    }
+    /// Where the files of `include` come from, default: the file system
+    pub fn with_source_loader(self, loader: impl SourceLoader + 'static) -> Self {
+        Self {
+            loader: Box::new(loader),
+            ..self
+        }
+    }
+    /// Features and libraries which `cond-expand` tests against, default: none
+    pub fn with_features(self, features: Features) -> Self {
+        Self { features, ..self }
+    }
+    /// How deep expansions could nest before they are reported as runaway, default:
+    /// `DEFAULT_EXPANSION_LIMIT`
+    pub fn with_expansion_limit(self, expansion_limit: usize) -> Self {
+        Self {
+            expansion_limit,
+            ..self
+        }
+    }
+    /// Enabled builtins and registered transformers, in the order they run
+    pub fn transformers(&self) -> impl Iterator<Item = &str> {
+        transformer_names(&self.builtins, &self.transformers)
+    }
+    pub fn build(self) -> Parser {
+        Parser {
+            transformers: self.transformers,
+            builtins: self.builtins,
+            loader: self.loader,
+            features: self.features,
+            expansion_limit: self.expansion_limit,
+            sources: SourceMap::default(),
+            expansions: Expansions::default(),
+            warnings: Vec::new(),
+        }
+    }
+    fn position(&self, name: &str) -> Option<usize> {
+        self.transformers
+            .iter()
+            .position(|(registered, _)| registered == name)
+    }
+}
 
-                let assign = enclosed(just(Token::Keyword("set!")).ignore_then(ident).then(expr))
-                    .map(|(ident, expr)| Expression::Assignment(ident, Box::new(expr)));
+/// A name which is not one of `BUILTINS`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownBuiltin(pub String);
 
-                map_err_category!(
-                    "<expression>",
-                    spanned(
-                        primitive
-                            .or(if_expr)
-                            .or(lambda)
-                            .or(assign)
-                            .or(proc_call)
-                            .recover_with(nested_delimiters(
-                                Token::Keyword("("),
-                                Token::Keyword(")"),
-                                [],
-                                |_| { Expression::Error },
-                            ))
-                    )
-                    .labelled("expression")
-                )
-            });
-            let def_proc = enclosed(
-                just(Token::Keyword("define"))
-                    .ignore_then(spanned(enclosed(ident.then(ident.repeated()))))
-                    .then(proc_body),
-            )
-            .map(|(((ident, args), formal_span), body)| {
-                Definition(ident, {
-                    let body_span_end = body.last_expr.1.end;
-                    (
-                        Expression::Procedure {
-                            args,
-                            body: Box::new(body),
-                        },
-                        formal_span.start..body_span_end,
-                    )
-                })
-            });
-            let def = map_err_category!(
-                "<definition>",
-                spanned(
-                    def_proc.or(enclosed(
-                        just(Token::Keyword("define"))
-                            .ignore_then(ident)
-                            .then(expr.clone())
-                    )
-                    .map(|(ident, expr)| Definition(ident, expr)))
-                )
-                .labelled("definition")
-            );
-            def.repeated()
-                .then(expr.repeated().at_least(1))
-                .map(|(defs, exprs)| {
-                    let len = exprs.len();
-                    let mut iter = exprs.into_iter();
-                    let mut exprs = Vec::new();
-                    for _ in 0..len - 1 {
-                        exprs.push(iter.next().unwrap());
-                    }
-                    let last_expr = iter.next().unwrap();
-                    Program {
-                        defs,
-                        exprs,
-                        last_expr,
-                    }
-                })
-                .labelled("procedure body")
-        });
-        proc_body.labelled("program").then_ignore(end())
+impl std::fmt::Display for UnknownBuiltin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Unknown builtin {}, expect one of {}",
+            self.0,
+            BUILTINS.join(", ")
+        )
     }
 }
+impl std::error::Error for UnknownBuiltin {}
 
-pub struct Parser {
-    transformers: Vec<Box<dyn Transformer>>,
+fn builtin(name: &str) -> Result<&'static str, UnknownBuiltin> {
+    BUILTINS
+        .iter()
+        .copied()
+        .find(|builtin| *builtin == name)
+        .ok_or_else(|| UnknownBuiltin(name.to_owned()))
 }
 
-impl Default for Parser {
-    fn default() -> Self {
-        Self {
-            transformers: vec![Box::new(crate::transformer::builtin::Begin)],
-        }
-    }
+fn transformer_names<'a>(
+    builtins: &'a [&'static str],
+    transformers: &'a [(String, Box<dyn Transformer>)],
+) -> impl Iterator<Item = &'a str> {
+    builtins
+        .iter()
+        .copied()
+        .chain(transformers.iter().map(|(name, _)| name.as_str()))
 }
 
 #[derive(From, Display, Debug)]
@@ -203,22 +202,73 @@ impl TokenizeOrParseError {
 }
 
 impl Parser {
+    pub fn builder() -> ParserBuilder {
+        ParserBuilder::default()
+    }
+    /// Enabled builtins and registered transformers, in the order they run
+    pub fn transformers(&self) -> impl Iterator<Item = &str> {
+        transformer_names(&self.builtins, &self.transformers)
+    }
+    /// Warnings of the last parse, which are also rendered with its errors
+    pub fn warnings(&self) -> Option<ParseError<Token>> {
+        let main = self.sources.files().first()?;
+        if self.warnings.is_empty() {
+            return None;
+        }
+        Some(
+            ParseError::new(main.source.clone(), main.path.clone(), Vec::new(), "token")
+                .with_warnings(self.warnings.clone())
+                .with_sources(self.sources.clone())
+                .with_expansions(self.expansions.clone()),
+        )
+    }
+    /// Sources of the last parse, spans of the parsed program are offsets in them
+    pub fn sources(&self) -> &SourceMap {
+        &self.sources
+    }
+    /// Expansions of the last parse, which tell the spans made up by transformers
+    pub fn expansions(&self) -> &Expansions {
+        &self.expansions
+    }
     pub fn parse_tokens(
         &mut self, // We might add new transformer in self
         tokens: &[Spanned<Token>],
         source: &str,
         source_path: &str,
     ) -> Result<Program, TokenizeOrParseError> {
-        use ::chumsky::Parser as _;
-        let tokens = expansion(&mut self.transformers, tokens, source, source_path)?;
-        chumsky::parser()
-            .parse(::chumsky::Stream::from_iter(
-                source.len()..source.len() + 1,
-                tokens.into_iter(),
-            ))
-            .map_err(|e| {
-                ParseError::new(source.to_owned(), source_path.to_owned(), e, "token").into()
-            })
+        self.sources = SourceMap::new(source_path, source);
+        let mut context = Context::new(source_path, &self.features);
+        let datums = expansion(
+            &self.transformers,
+            &self.builtins,
+            self.loader.as_ref(),
+            &mut context,
+            self.expansion_limit,
+            &mut self.sources,
+            tokens,
+        )?;
+        let Context {
+            mut errors,
+            warnings,
+            expansions,
+            ..
+        } = context;
+        self.warnings = warnings;
+        self.expansions = expansions;
+        let program = convert(datums, source.len());
+        match program {
+            Ok(program) if errors.is_empty() => Ok(program),
+            program => {
+                errors.extend(program.err().into_iter().flatten());
+                Err(
+                    ParseError::new(source.to_owned(), source_path.to_owned(), errors, "token")
+                        .with_warnings(self.warnings.clone())
+                        .with_sources(self.sources.clone())
+                        .with_expansions(self.expansions.clone())
+                        .into(),
+                )
+            }
+        }
     }
     pub fn parse(
         &mut self, // We might add new transformer in self
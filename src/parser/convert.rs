@@ -0,0 +1,795 @@
+use std::{iter::Peekable, vec::IntoIter};
+
+use ::chumsky::{error::Error as _, prelude::Simple};
+use num::Signed;
+
+use crate::*;
+
+/// Identifiers which introduce a derived syntax, they could not be used as expressions or be bound
+const SYNTAX_IDENTIFIERS: &[&str] = &[
+    "define-library",
+    "import",
+    "export",
+    "include",
+    "include-ci",
+    "include-library-declarations",
+    "guard",
+    "else",
+    "=>",
+    "define-values",
+    "let-values",
+    "let*-values",
+    "receive",
+    "begin",
+    "quote",
+];
+
+/// What could follow `(` in an expression
+const EXPRESSION_HEADS: &[&str] = &[
+    "<expression>",
+    "begin",
+    "guard",
+    "if",
+    "lambda",
+    "let*-values",
+    "let-values",
+    "quote",
+    "receive",
+    "set!",
+];
+
+const DECLARATION_HEADS: &[&str] = &[
+    "begin",
+    "export",
+    "import",
+    "include",
+    "include-ci",
+    "include-library-declarations",
+];
+
+/// Errors are boxed, as they are much larger than what is converted
+type Result<T> = std::result::Result<T, Box<Simple<Token>>>;
+
+/// Convert expanded datums into a `Program`, `eoi` is the offset of the end of input.
+///
+/// Errors are recovered at the nearest expression or definition, so that every error is reported.
+pub fn convert(
+    datums: Vec<Spanned<Datum>>,
+    eoi: usize,
+) -> std::result::Result<Program, Vec<Simple<Token>>> {
+    let mut converter = Converter { errors: Vec::new() };
+    let program = converter.program(datums, eoi);
+    if converter.errors.is_empty() {
+        Ok(program)
+    } else {
+        Err(converter.errors)
+    }
+}
+
+/// Convert an expanded datum into an `Expression`
+pub fn convert_expression(
+    datum: Spanned<Datum>,
+) -> std::result::Result<Spanned<Expression>, Vec<Simple<Token>>> {
+    let mut converter = Converter { errors: Vec::new() };
+    let expr = converter.expr(datum);
+    if converter.errors.is_empty() {
+        Ok(expr)
+    } else {
+        Err(converter.errors)
+    }
+}
+
+fn unexpected(
+    (datum, span): &Spanned<Datum>,
+    expected: &[&'static str],
+    label: &'static str,
+) -> Box<Simple<Token>> {
+    let (found, span) = match datum {
+        Datum::List(_) => (Token::Keyword("("), span.start..span.start + 1),
+        Datum::Keyword(keyword) => (Token::Keyword(keyword), span.clone()),
+        Datum::Primitive(primitive) => (Token::Primitive(primitive.clone()), span.clone()),
+        Datum::Error => (Token::Keyword("<error>"), span.clone()),
+    };
+    Box::new(
+        Simple::expected_input_found(
+            span,
+            expected
+                .iter()
+                .map(|expected| Some(Token::Keyword(expected))),
+            Some(found),
+        )
+        .with_label(label),
+    )
+}
+
+/// The head of `datum` if it is a list which starts with one of `names`
+fn head(datum: &Spanned<Datum>, names: &[&'static str]) -> Option<&'static str> {
+    let name = match datum {
+        (Datum::List(list), _) => match list.first() {
+            Some((Datum::Keyword(keyword), _)) => *keyword,
+            Some((Datum::Primitive(Primitive::Ident(ident)), _)) => ident.as_str(),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    names.iter().find(|head| **head == name).copied()
+}
+
+/// `(define ...)`, `(define-values ...)` or `(begin def ...)`
+fn is_definition(datum: &Spanned<Datum>) -> bool {
+    match head(datum, &["define", "define-values", "begin"]) {
+        Some("begin") => match datum {
+            (Datum::List(list), _) => list.len() > 1 && list[1..].iter().all(is_definition),
+            _ => false,
+        },
+        head => head.is_some(),
+    }
+}
+
+fn ident(
+    datum: Spanned<Datum>,
+    expected: &[&'static str],
+    label: &'static str,
+) -> Result<Spanned<String>> {
+    match datum {
+        (Datum::Primitive(Primitive::Ident(ident)), span) => Ok((ident, span)),
+        datum => Err(unexpected(&datum, expected, label)),
+    }
+}
+
+/// An identifier which is bound or assigned, syntax identifiers could not be referenced so they
+/// are not bound either
+fn variable(
+    datum: Spanned<Datum>,
+    expected: &[&'static str],
+    label: &'static str,
+) -> Result<Spanned<String>> {
+    match datum {
+        (Datum::Primitive(Primitive::Ident(ident)), span)
+            if !SYNTAX_IDENTIFIERS.contains(&ident.as_str()) =>
+        {
+            Ok((ident, span))
+        }
+        datum => Err(unexpected(&datum, expected, label)),
+    }
+}
+
+fn string(datum: Spanned<Datum>, label: &'static str) -> Result<Spanned<String>> {
+    match datum {
+        (Datum::Primitive(Primitive::String(string)), span) => Ok((string, span)),
+        datum => Err(unexpected(&datum, &[")", "<string>"], label)),
+    }
+}
+
+/// Elements of a list, errors of missing elements are reported at `)`
+struct List {
+    items: Peekable<IntoIter<Spanned<Datum>>>,
+    /// The span of `)`, or of the end of input for the top level
+    close: Span,
+    top_level: bool,
+    label: &'static str,
+}
+
+impl List {
+    fn new(items: Vec<Spanned<Datum>>, span: &Span, label: &'static str) -> Self {
+        Self {
+            items: items.into_iter().peekable(),
+            close: span.end.saturating_sub(1)..span.end,
+            top_level: false,
+            label,
+        }
+    }
+    /// `datum` should be a list
+    fn of(datum: Spanned<Datum>, expected: &[&'static str], label: &'static str) -> Result<Self> {
+        match datum {
+            (Datum::List(list), span) => Ok(Self::new(list, &span, label)),
+            datum => Err(unexpected(&datum, expected, label)),
+        }
+    }
+    fn top_level(items: Vec<Spanned<Datum>>, eoi: usize) -> Self {
+        Self {
+            items: items.into_iter().peekable(),
+            close: eoi..eoi + 1,
+            top_level: true,
+            label: "program",
+        }
+    }
+    fn label(self, label: &'static str) -> Self {
+        Self { label, ..self }
+    }
+    fn peek(&mut self) -> Option<&Spanned<Datum>> {
+        self.items.peek()
+    }
+    fn next_if(&mut self, f: impl FnOnce(&Spanned<Datum>) -> bool) -> Option<Spanned<Datum>> {
+        self.items.next_if(f)
+    }
+    /// The next element, which should exist
+    fn expect(&mut self, expected: &[&'static str]) -> Result<Spanned<Datum>> {
+        match self.items.next() {
+            Some(datum) => Ok(datum),
+            None => Err(self.missing(expected)),
+        }
+    }
+    fn missing(&self, expected: &[&'static str]) -> Box<Simple<Token>> {
+        Box::new(
+            Simple::expected_input_found(
+                self.close.clone(),
+                expected
+                    .iter()
+                    .map(|expected| Some(Token::Keyword(expected))),
+                (!self.top_level).then_some(Token::Keyword(")")),
+            )
+            .with_label(self.label),
+        )
+    }
+    /// Every element should have been consumed
+    fn end(mut self) -> Result<()> {
+        match self.items.next() {
+            Some(datum) => Err(unexpected(&datum, &[")"], self.label)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Iterator for List {
+    type Item = Spanned<Datum>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.items.next()
+    }
+}
+
+struct Converter {
+    errors: Vec<Simple<Token>>,
+}
+
+impl Converter {
+    fn recover<T>(&mut self, result: Result<T>) -> Option<T> {
+        result.map_err(|e| self.errors.push(*e)).ok()
+    }
+
+    fn program(&mut self, datums: Vec<Spanned<Datum>>, eoi: usize) -> Program {
+        let mut items = List::top_level(datums, eoi);
+        let mut libraries = Vec::new();
+        let mut defines_library = false;
+        while let Some(datum) = items.next_if(|datum| head(datum, &["define-library"]).is_some()) {
+            defines_library = true;
+            let library = self.library(datum);
+            libraries.extend(self.recover(library));
+        }
+        let mut imports = Vec::new();
+        while let Some(datum) = items.next_if(|datum| head(datum, &["import"]).is_some()) {
+            let span = datum.1.clone();
+            let sets = self.import(datum);
+            imports.extend(self.recover(sets).map(|sets| (sets, span)));
+        }
+        // A source which only defines libraries does not need a body
+        let body = if !defines_library || items.peek().is_some() {
+            let body = self.body(&mut items, true);
+            self.recover(body)
+        } else {
+            None
+        };
+        Program {
+            libraries,
+            imports,
+            body,
+        }
+    }
+
+    /// Definitions followed by at least one expression. At the top level, definitions in `begin`
+    /// are spliced into the top level scope, so `begin` may mix definitions and expressions.
+    fn body(&mut self, items: &mut List, top_level: bool) -> Result<ProcedureBody> {
+        let mut defs = Vec::new();
+        while let Some(datum) = items.next_if(is_definition) {
+            let def = self.definition(datum);
+            defs.extend(self.recover(def));
+        }
+        let mut exprs = Vec::new();
+        for datum in items.by_ref() {
+            exprs.push(if top_level {
+                self.top_level_expr(datum)
+            } else {
+                self.expr(datum)
+            });
+        }
+        let last_expr = exprs
+            .pop()
+            .ok_or_else(|| items.missing(&["<definition>", "<expression>"]))?;
+        Ok(ProcedureBody {
+            defs,
+            exprs,
+            last_expr,
+        })
+    }
+
+    fn top_level_expr(&mut self, datum: Spanned<Datum>) -> Spanned<Expression> {
+        let is_splicing = head(&datum, &["begin"]).is_some()
+            && matches!(&datum.0, Datum::List(list) if list.get(1).is_some_and(is_definition));
+        if !is_splicing {
+            return self.expr(datum);
+        }
+        let (Datum::List(list), span) = datum else {
+            unreachable!()
+        };
+        let mut items = List::new(list, &span, "begin");
+        items.next();
+        let mut defs = Vec::new();
+        while let Some(datum) = items.next_if(is_definition) {
+            let def = self.definition(datum);
+            defs.extend(self.recover(def));
+        }
+        let exprs = items.map(|datum| self.top_level_expr(datum)).collect();
+        (Expression::Begin { defs, exprs }, span)
+    }
+
+    fn definition(&mut self, datum: Spanned<Datum>) -> Result<Spanned<Definition>> {
+        let span = datum.1.clone();
+        let head = head(&datum, &["define", "define-values", "begin"]);
+        let mut items = List::of(datum, &["<definition>"], "definition")?;
+        items.next();
+        let def = match head {
+            Some("define") => match items.expect(&["(", "<identifier>"])? {
+                (Datum::List(formals), formals_span) => {
+                    let mut formals = List::new(formals, &formals_span, "definition");
+                    let name = variable(
+                        formals.expect(&["<identifier>"])?,
+                        &["<identifier>"],
+                        "definition",
+                    )?;
+                    let Formals { args, rest } = self.formals_tail(formals)?;
+                    let body = self.body(&mut items, false)?;
+                    let end = body.last_expr.1.end;
+                    Definition::Variable(
+                        name,
+                        (
+                            Expression::Procedure {
+                                args,
+                                rest,
+                                body: Box::new(body),
+                            },
+                            formals_span.start..end,
+                        ),
+                    )
+                }
+                datum => {
+                    let name = variable(datum, &["(", "<identifier>"], "definition")?;
+                    let expr = self.expr(items.expect(&["<expression>"])?);
+                    items.end()?;
+                    Definition::Variable(name, expr)
+                }
+            },
+            Some("define-values") => {
+                let formals = self.formals(items.expect(&["(", "<identifier>"])?, "definition")?;
+                let expr = self.expr(items.expect(&["<expression>"])?);
+                items.end()?;
+                Definition::Values(formals, expr)
+            }
+            _ => Definition::Begin(
+                items
+                    .map(|datum| self.definition(datum))
+                    .collect::<Result<_>>()?,
+            ),
+        };
+        Ok((def, span))
+    }
+
+    /// `(args ... . rest)` or `rest`, errors are labelled by the form which binds them
+    fn formals(&mut self, datum: Spanned<Datum>, label: &'static str) -> Result<Spanned<Formals>> {
+        let span = datum.1.clone();
+        let formals = match datum {
+            (Datum::List(list), span) => self.formals_tail(List::new(list, &span, label))?,
+            datum => Formals {
+                args: Vec::new(),
+                rest: Some(variable(datum, &["(", "<identifier>"], label)?),
+            },
+        };
+        Ok((formals, span))
+    }
+
+    fn formals_tail(&mut self, mut items: List) -> Result<Formals> {
+        let mut args = Vec::new();
+        while let Some(datum) = items.next() {
+            if let (Datum::Keyword("."), _) = datum {
+                let rest = variable(
+                    items.expect(&["<identifier>"])?,
+                    &["<identifier>"],
+                    items.label,
+                )?;
+                items.end()?;
+                return Ok(Formals {
+                    args,
+                    rest: Some(rest),
+                });
+            }
+            args.push(variable(datum, &[")", ".", "<identifier>"], items.label)?);
+        }
+        Ok(Formals { args, rest: None })
+    }
+
+    /// An expression, which is `Expression::Error` if it is invalid
+    fn expr(&mut self, datum: Spanned<Datum>) -> Spanned<Expression> {
+        let span = datum.1.clone();
+        let expr = self.try_expr(datum);
+        (self.recover(expr).unwrap_or(Expression::Error), span)
+    }
+
+    fn try_expr(&mut self, datum: Spanned<Datum>) -> Result<Expression> {
+        let (list, span) = match datum {
+            (Datum::Primitive(Primitive::Ident(ident)), _)
+                if !SYNTAX_IDENTIFIERS.contains(&ident.as_str()) =>
+            {
+                return Ok(Expression::Primitive(Primitive::Ident(ident)))
+            }
+            (Datum::Primitive(primitive @ Primitive::Ident(_)), span) => {
+                return Err(unexpected(
+                    &(Datum::Primitive(primitive), span),
+                    &["<expression>"],
+                    "expression",
+                ))
+            }
+            (Datum::Primitive(primitive), _) => return Ok(Expression::Primitive(primitive)),
+            (Datum::Error, _) => return Ok(Expression::Error),
+            (datum @ Datum::Keyword(_), span) => {
+                return Err(unexpected(&(datum, span), &["<expression>"], "expression"))
+            }
+            (Datum::List(list), span) => (list, span),
+        };
+        let mut items = List::new(list, &span, "expression");
+        let Some(first) = items.peek() else {
+            return Err(items.missing(EXPRESSION_HEADS));
+        };
+        let syntax = match first {
+            (Datum::Keyword(keyword), _) => Some(*keyword),
+            (Datum::Primitive(Primitive::Ident(ident)), _) => SYNTAX_IDENTIFIERS
+                .iter()
+                .find(|syntax| **syntax == ident)
+                .copied(),
+            _ => None,
+        };
+        match syntax {
+            Some("if") => {
+                items.next();
+                let mut items = items.label("if");
+                let test = self.expr(items.expect(&["<expression>"])?);
+                let conseq = self.expr(items.expect(&["<expression>"])?);
+                let alter = items.next().map(|datum| self.expr(datum));
+                items.end()?;
+                Ok(Expression::Conditional {
+                    test: Box::new(test),
+                    conseq: Box::new(conseq),
+                    alter: alter.map(Box::new),
+                })
+            }
+            Some("lambda") => {
+                items.next();
+                let mut items = items.label("lambda");
+                let (Formals { args, rest }, _) =
+                    self.formals(items.expect(&["(", "<identifier>"])?, "lambda")?;
+                let body = self.body(&mut items, false)?;
+                Ok(Expression::Procedure {
+                    args,
+                    rest,
+                    body: Box::new(body),
+                })
+            }
+            Some("set!") => {
+                items.next();
+                let mut items = items.label("assignment");
+                let name = variable(
+                    items.expect(&["<identifier>"])?,
+                    &["<identifier>"],
+                    "assignment",
+                )?;
+                let expr = self.expr(items.expect(&["<expression>"])?);
+                items.end()?;
+                Ok(Expression::Assignment(name, Box::new(expr)))
+            }
+            Some(syntax @ ("let-values" | "let*-values")) => {
+                items.next();
+                let mut items = items.label("let-values");
+                let bindings = List::of(items.expect(&["("])?, &["("], "let-values")?
+                    .map(|datum| {
+                        let span = datum.1.clone();
+                        let mut binding = List::of(datum, &[")", "("], "let-values")?;
+                        let formals =
+                            self.formals(binding.expect(&["(", "<identifier>"])?, "let-values")?;
+                        let expr = self.expr(binding.expect(&["<expression>"])?);
+                        binding.end()?;
+                        Ok((ValuesBinding(formals, expr), span))
+                    })
+                    .collect::<Result<_>>()?;
+                let body = self.body(&mut items, false)?;
+                Ok(Expression::LetValues {
+                    sequential: syntax == "let*-values",
+                    bindings,
+                    body: Box::new(body),
+                })
+            }
+            Some("receive") => {
+                items.next();
+                let mut items = items.label("receive");
+                let formals = self.formals(items.expect(&["(", "<identifier>"])?, "receive")?;
+                let expr = self.expr(items.expect(&["<expression>"])?);
+                let body = self.body(&mut items, false)?;
+                Ok(Expression::Receive {
+                    formals,
+                    expr: Box::new(expr),
+                    body: Box::new(body),
+                })
+            }
+            Some("guard") => {
+                items.next();
+                let mut items = items.label("guard");
+                let mut spec = List::of(items.expect(&["("])?, &["("], "guard")?;
+                let var = variable(spec.expect(&["<identifier>"])?, &["<identifier>"], "guard")?;
+                let first = spec.expect(&["("])?;
+                let clauses = Some(first)
+                    .into_iter()
+                    .chain(spec)
+                    .map(|datum| self.cond_clause(datum))
+                    .collect::<Result<Vec<_>>>()?;
+                let len = clauses.len();
+                for (i, (clause, span)) in clauses.iter().enumerate() {
+                    if matches!(clause, CondClause::Else(_)) && i + 1 != len {
+                        return Err(Box::new(else_not_last(span.clone(), "guard")));
+                    }
+                }
+                let body = self.body(&mut items, false)?;
+                Ok(Expression::Guard {
+                    var,
+                    clauses,
+                    body: Box::new(body),
+                })
+            }
+            Some("quote") => {
+                items.next();
+                let mut items = items.label("quote");
+                let datum = items.expect(&["<datum>"])?;
+                items.end()?;
+                Ok(Expression::Quote(datum))
+            }
+            Some("begin") => {
+                items.next();
+                let mut items = items.label("begin");
+                let first = self.expr(items.expect(&["<expression>"])?);
+                let exprs = Some(first)
+                    .into_iter()
+                    .chain(items.map(|datum| self.expr(datum)))
+                    .collect();
+                Ok(Expression::Begin {
+                    defs: Vec::new(),
+                    exprs,
+                })
+            }
+            Some(_) => {
+                let first = items.next().unwrap();
+                Err(unexpected(&first, EXPRESSION_HEADS, "expression"))
+            }
+            None => {
+                let operator = self.expr(items.next().unwrap());
+                let args = items.map(|datum| self.expr(datum)).collect();
+                Ok(Expression::ProcedureCall {
+                    operator: Box::new(operator),
+                    args,
+                })
+            }
+        }
+    }
+
+    fn cond_clause(&mut self, datum: Spanned<Datum>) -> Result<Spanned<CondClause>> {
+        let span = datum.1.clone();
+        let is_else = head(&datum, &["else"]).is_some();
+        let is_arrow = matches!(
+            &datum.0,
+            Datum::List(list) if matches!(
+                list.get(1),
+                Some((Datum::Primitive(Primitive::Ident(ident)), _)) if ident == "=>"
+            )
+        );
+        let mut items = List::of(datum, &[")", "("], "cond clause")?;
+        let clause = if is_else {
+            items.next();
+            let first = self.expr(items.expect(&["<expression>"])?);
+            CondClause::Else(
+                Some(first)
+                    .into_iter()
+                    .chain(items.map(|datum| self.expr(datum)))
+                    .collect(),
+            )
+        } else if is_arrow {
+            let test = self.expr(items.next().unwrap());
+            items.next();
+            let receiver = self.expr(items.expect(&["<expression>"])?);
+            items.end()?;
+            CondClause::Arrow { test, receiver }
+        } else {
+            let test = self.expr(items.expect(&["<expression>", "else"])?);
+            CondClause::Test {
+                test,
+                body: items.map(|datum| self.expr(datum)).collect(),
+            }
+        };
+        Ok((clause, span))
+    }
+
+    fn library(&mut self, datum: Spanned<Datum>) -> Result<Spanned<Library>> {
+        let span = datum.1.clone();
+        let mut items = List::of(datum, &["("], "library")?;
+        items.next();
+        let name = self.library_name(items.expect(&["("])?)?;
+        let mut declarations = Vec::new();
+        for datum in items {
+            let declaration = self.declaration(datum);
+            declarations.extend(self.recover(declaration));
+        }
+        Ok((Library { name, declarations }, span))
+    }
+
+    fn library_name(&mut self, datum: Spanned<Datum>) -> Result<Spanned<LibraryName>> {
+        let span = datum.1.clone();
+        let mut items = List::of(datum, &["("], "library name")?;
+        let mut name = Vec::new();
+        let mut expected: &[&'static str] = &["<library name part>"];
+        for part in items.by_ref() {
+            match part {
+                (Datum::Primitive(p @ Primitive::Ident(_)), span) => name.push((p, span)),
+                (Datum::Primitive(Primitive::Integer(i)), span) if !i.is_negative() => {
+                    name.push((Primitive::Integer(i), span))
+                }
+                part => return Err(unexpected(&part, expected, "library name")),
+            }
+            expected = &[")", "<library name part>"];
+        }
+        if name.is_empty() {
+            return Err(items.missing(expected));
+        }
+        Ok((name, span))
+    }
+
+    fn declaration(&mut self, datum: Spanned<Datum>) -> Result<Spanned<LibraryDeclaration>> {
+        let span = datum.1.clone();
+        let head = head(&datum, DECLARATION_HEADS);
+        let mut items = List::of(datum, &["("], "library declaration")?;
+        let declaration = match head {
+            Some("export") => {
+                items.next();
+                LibraryDeclaration::Export(
+                    items
+                        .map(|datum| self.export_spec(datum))
+                        .collect::<Result<_>>()?,
+                )
+            }
+            Some("import") => {
+                items.next();
+                LibraryDeclaration::Import(
+                    items
+                        .map(|datum| self.import_set(datum))
+                        .collect::<Result<_>>()?,
+                )
+            }
+            Some("begin") => {
+                items.next();
+                let mut defs = Vec::new();
+                while let Some(datum) = items.next_if(is_definition) {
+                    defs.push(self.definition(datum)?);
+                }
+                let exprs = items.map(|datum| self.expr(datum)).collect();
+                LibraryDeclaration::Begin { defs, exprs }
+            }
+            Some(head @ ("include" | "include-ci" | "include-library-declarations")) => {
+                items.next();
+                let first = string(items.expect(&["<string>"])?, "library declaration")?;
+                let files = Some(Ok(first))
+                    .into_iter()
+                    .chain(items.map(|datum| string(datum, "library declaration")))
+                    .collect::<Result<_>>()?;
+                match head {
+                    "include-library-declarations" => {
+                        LibraryDeclaration::IncludeLibraryDeclarations(files)
+                    }
+                    _ => LibraryDeclaration::Include {
+                        case_insensitive: head == "include-ci",
+                        files,
+                    },
+                }
+            }
+            _ => {
+                let datum = items.next();
+                return Err(match datum {
+                    Some(datum) => unexpected(&datum, DECLARATION_HEADS, "library declaration"),
+                    None => items.missing(DECLARATION_HEADS),
+                });
+            }
+        };
+        Ok((declaration, span))
+    }
+
+    fn export_spec(&mut self, datum: Spanned<Datum>) -> Result<Spanned<ExportSpec>> {
+        let span = datum.1.clone();
+        let spec = match datum {
+            (Datum::Primitive(Primitive::Ident(ident)), _) => ExportSpec::Ident(ident),
+            datum if head(&datum, &["rename"]).is_some() => {
+                let mut items = List::of(datum, &[], "export spec")?;
+                items.next();
+                let rename = self.rename(items)?;
+                ExportSpec::Rename(rename)
+            }
+            datum => {
+                return Err(unexpected(
+                    &datum,
+                    &[")", "(", "<identifier>"],
+                    "export spec",
+                ))
+            }
+        };
+        Ok((spec, span))
+    }
+
+    /// `from to)`
+    fn rename(&mut self, mut items: List) -> Result<Rename> {
+        let from = ident(
+            items.expect(&["<identifier>"])?,
+            &["<identifier>"],
+            items.label,
+        )?;
+        let to = ident(
+            items.expect(&["<identifier>"])?,
+            &["<identifier>"],
+            items.label,
+        )?;
+        items.end()?;
+        Ok(Rename(from, to))
+    }
+
+    fn import(&mut self, datum: Spanned<Datum>) -> Result<Vec<Spanned<ImportSet>>> {
+        let mut items = List::of(datum, &["("], "import")?;
+        items.next();
+        items.map(|datum| self.import_set(datum)).collect()
+    }
+
+    fn import_set(&mut self, datum: Spanned<Datum>) -> Result<Spanned<ImportSet>> {
+        let span = datum.1.clone();
+        let head = head(&datum, &["only", "except", "prefix", "rename"]);
+        let is_modifier = head.is_some()
+            && matches!(&datum.0, Datum::List(list) if matches!(list.get(1), Some((Datum::List(_), _))));
+        if !is_modifier {
+            let name = self
+                .library_name(datum)
+                .map_err(|e| Box::new(e.with_label("import set")))?;
+            return Ok((ImportSet::Library(name.0), span));
+        }
+        let mut items = List::of(datum, &["("], "import set")?;
+        items.next();
+        let set = Box::new(self.import_set(items.expect(&["("])?)?);
+        let idents = |items: List| {
+            items
+                .map(|datum| ident(datum, &[")", "<identifier>"], "import set"))
+                .collect::<Result<Vec<_>>>()
+        };
+        let set = match head {
+            Some("only") => ImportSet::Only(set, idents(items)?),
+            Some("except") => ImportSet::Except(set, idents(items)?),
+            Some("prefix") => {
+                let prefix = ident(
+                    items.expect(&["<identifier>"])?,
+                    &["<identifier>"],
+                    "import set",
+                )?;
+                items.end()?;
+                ImportSet::Prefix(set, prefix)
+            }
+            _ => ImportSet::Rename(
+                set,
+                items
+                    .map(|datum| {
+                        let span = datum.1.clone();
+                        let items = List::of(datum, &[")", "("], "import set")?;
+                        Ok((self.rename(items)?, span))
+                    })
+                    .collect::<Result<_>>()?,
+            ),
+        };
+        Ok((set, span))
+    }
+}
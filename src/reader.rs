@@ -0,0 +1,72 @@
+use ::chumsky::{error::Error as _, prelude::Simple};
+
+use crate::{transformer::datums_at, *};
+
+/// Read the first datum of `source` as `read` does, returns it and the rest of `source`.
+///
+/// Spans are offsets in `source`. Only the chars up to the end of the datum are lexed, so errors
+/// after it are left to the rest. The whitespaces and comments after the datum are skipped, so the
+/// rest is empty at the end of input.
+pub fn read_datum<'a>(
+    source: &'a str,
+    source_path: &str,
+) -> Result<(Spanned<Datum>, &'a str), TokenizeOrParseError> {
+    let mut tokens = Vec::new();
+    // The end of the last token in chars and in bytes
+    let (mut offset, mut byte) = (0, 0);
+    let mut depth = 0usize;
+    loop {
+        let next = next_token(&source[byte..], offset).map_err(|e| {
+            ParseError::new(source.to_owned(), source_path.to_owned(), e, "char")
+                .display_every_expected(false)
+        })?;
+        let Some((token, end)) = next else {
+            break;
+        };
+        byte += source[byte..]
+            .char_indices()
+            .nth(end - offset)
+            .map_or(source.len() - byte, |(i, _)| i);
+        offset = end;
+        let complete = match &token.0 {
+            Token::Keyword("(") => {
+                depth += 1;
+                false
+            }
+            Token::Keyword(")") => {
+                depth = depth.saturating_sub(1);
+                depth == 0
+            }
+            Token::Keyword(prefix) if abbreviation(prefix).is_some() => false,
+            _ => depth == 0,
+        };
+        tokens.push(token);
+        if complete {
+            break;
+        }
+    }
+    // Tokens only run out before the datum is complete at the end of input
+    let error =
+        |errors| ParseError::new(source.to_owned(), source_path.to_owned(), errors, "token");
+    let datum = datums_at(&tokens, offset)
+        .map_err(error)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| {
+            error(vec![Simple::expected_input_found(
+                offset..offset + 1,
+                [Some(Token::Keyword("<datum>"))],
+                None,
+            )])
+        })?;
+    Ok((datum, &source[byte..]))
+}
+
+/// Read every datum of `source`
+pub fn read_all(
+    source: &str,
+    source_path: &str,
+) -> Result<Vec<Spanned<Datum>>, TokenizeOrParseError> {
+    let tokens = tokenize(source, source_path)?;
+    Ok(transformer::datumize(&tokens, source, source_path)?)
+}
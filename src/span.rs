@@ -1,2 +1,142 @@
 pub type Span = std::ops::Range<usize>;
 pub type Spanned<T> = (T, Span);
+
+/// A source loaded while parsing
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub path: String,
+    pub source: String,
+    /// Offset of the first char of this source
+    pub offset: usize,
+}
+
+impl SourceFile {
+    /// Offsets covered by this source, including the end of input
+    pub fn range(&self) -> Span {
+        self.offset..self.offset + self.source.len() + 1
+    }
+}
+
+/// Sources loaded while parsing, such as the files of `include`.
+///
+/// Every source occupies distinct offsets, so a `Span` also tells which source it comes from.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    /// The main source starts at offset 0
+    pub fn new(path: &str, source: &str) -> Self {
+        let mut map = Self::default();
+        map.add(path, source);
+        map
+    }
+    /// Add a source after the existing ones, returns its offset
+    pub fn add(&mut self, path: &str, source: &str) -> usize {
+        let offset = self.files.last().map(|file| file.range().end).unwrap_or(0);
+        self.files.push(SourceFile {
+            path: path.to_owned(),
+            source: source.to_owned(),
+            offset,
+        });
+        offset
+    }
+    pub fn files(&self) -> &[SourceFile] {
+        &self.files
+    }
+    /// The source which covers `offset`
+    pub fn file(&self, offset: usize) -> Option<&SourceFile> {
+        let index = self
+            .files
+            .partition_point(|file| file.range().end <= offset);
+        self.files
+            .get(index)
+            .filter(|file| file.range().contains(&offset))
+    }
+    /// The source of `span` and the span relative to that source
+    pub fn locate(&self, span: &Span) -> Option<(&SourceFile, Span)> {
+        self.file(span.start)
+            .map(|file| (file, span.start - file.offset..span.end - file.offset))
+    }
+}
+
+/// A datum rewritten by a transformer
+#[derive(Debug, Clone)]
+pub struct Expansion {
+    /// The name of the transformer
+    pub transformer: String,
+    /// The span of the datum before it is rewritten
+    pub call_site: Span,
+    /// Spans of the datums which the transformer made up
+    generated: Vec<Span>,
+    /// Spans of the datums which the transformer moved from the call site
+    moved: Vec<Span>,
+}
+
+/// Expansions of transformers while parsing, in the order they happened.
+///
+/// A datum made up by a transformer has no source of its own, so its span is borrowed from the call
+/// site. These tell such a span from the spans of datums written in the source.
+#[derive(Debug, Clone, Default)]
+pub struct Expansions {
+    expansions: Vec<Expansion>,
+}
+
+impl Expansions {
+    pub fn add(
+        &mut self,
+        transformer: &str,
+        call_site: Span,
+        generated: Vec<Span>,
+        moved: Vec<Span>,
+    ) {
+        self.expansions.push(Expansion {
+            transformer: transformer.to_owned(),
+            call_site,
+            generated,
+            moved,
+        });
+    }
+    pub fn expansions(&self) -> &[Expansion] {
+        &self.expansions
+    }
+    pub fn is_empty(&self) -> bool {
+        self.expansions.is_empty()
+    }
+    /// The expansions which produced `span`, the innermost first, each one produced the call site of
+    /// the one before it
+    pub fn backtrace(&self, span: &Span) -> Vec<&Expansion> {
+        let mut backtrace = Vec::new();
+        let mut span = span.clone();
+        let mut before = self.expansions.len();
+        while let Some(index) = self.expansions[..before]
+            .iter()
+            .rposition(|expansion| expansion.generates(&span))
+        {
+            let expansion = &self.expansions[index];
+            backtrace.push(expansion);
+            span = expansion.call_site.clone();
+            before = index;
+        }
+        backtrace
+    }
+}
+
+impl Expansion {
+    /// Whether the innermost datum covering `span` is made up by this expansion
+    fn generates(&self, span: &Span) -> bool {
+        let covers = |outer: &Span| outer.start <= span.start && span.end <= outer.end;
+        let innermost = |spans: &[Span]| {
+            spans
+                .iter()
+                .filter(|outer| covers(outer))
+                .map(|outer| outer.len())
+                .min()
+        };
+        match (innermost(&self.generated), innermost(&self.moved)) {
+            (Some(generated), Some(moved)) => generated < moved,
+            (generated, _) => generated.is_some(),
+        }
+    }
+}
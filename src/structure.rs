@@ -0,0 +1,629 @@
+use std::{
+    hash::{Hash, Hasher},
+    mem::discriminant,
+};
+
+use crate::*;
+
+/// The first pair of nodes which differ besides their spans
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Difference {
+    /// What the nodes are, such as `expression` or `datum`
+    pub kind: &'static str,
+    pub left: Span,
+    pub right: Span,
+}
+
+/// How two trees compare when spans are left out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Comparison {
+    Same,
+    /// The nodes themselves differ, the nearest spanned parent tells where
+    Differ,
+    Child(Difference),
+}
+
+impl Comparison {
+    /// Compare the next children if these are the same
+    fn then(self, next: impl FnOnce() -> Comparison) -> Comparison {
+        match self {
+            Comparison::Same => next(),
+            differ => differ,
+        }
+    }
+}
+
+/// Equality, hashing and diff of trees which leave out the spans, so trees compare the same
+/// regardless of formatting
+pub trait Structure {
+    /// What the node is, for `Difference`
+    const KIND: &'static str;
+    fn hash_structure<H: Hasher>(&self, state: &mut H);
+    fn compare_structure(&self, other: &Self) -> Comparison;
+    fn same_structure(&self, other: &Self) -> bool {
+        self.compare_structure(other) == Comparison::Same
+    }
+}
+
+/// The first pair of nodes which differ besides their spans
+pub fn diff<T: Structure>(left: &Spanned<T>, right: &Spanned<T>) -> Option<Difference> {
+    match left.compare_structure(right) {
+        Comparison::Same => None,
+        Comparison::Differ => Some(Difference {
+            kind: T::KIND,
+            left: left.1.clone(),
+            right: right.1.clone(),
+        }),
+        Comparison::Child(difference) => Some(difference),
+    }
+}
+
+impl Program {
+    /// The first pair of nodes which differ besides their spans, the spans of the programs if they
+    /// differ in the number of items
+    pub fn diff(&self, other: &Program) -> Option<Difference> {
+        diff(&(self, self.span()), &(other, other.span()))
+    }
+}
+
+/// Compare and hash the wrapped tree without spans, such as to collect programs in a `HashSet`
+#[derive(Debug, Clone)]
+pub struct IgnoreSpans<T>(pub T);
+
+impl<T: Structure> PartialEq for IgnoreSpans<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.same_structure(&other.0)
+    }
+}
+
+impl<T: Structure> Eq for IgnoreSpans<T> {}
+
+impl<T: Structure> Hash for IgnoreSpans<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash_structure(state)
+    }
+}
+
+impl<T: Structure> Structure for Spanned<T> {
+    const KIND: &'static str = T::KIND;
+    fn hash_structure<H: Hasher>(&self, state: &mut H) {
+        self.0.hash_structure(state)
+    }
+    fn compare_structure(&self, other: &Self) -> Comparison {
+        match self.0.compare_structure(&other.0) {
+            Comparison::Differ => Comparison::Child(Difference {
+                kind: T::KIND,
+                left: self.1.clone(),
+                right: other.1.clone(),
+            }),
+            comparison => comparison,
+        }
+    }
+}
+
+impl<T: Structure> Structure for &T {
+    const KIND: &'static str = T::KIND;
+    fn hash_structure<H: Hasher>(&self, state: &mut H) {
+        (*self).hash_structure(state)
+    }
+    fn compare_structure(&self, other: &Self) -> Comparison {
+        (*self).compare_structure(other)
+    }
+}
+
+impl<T: Structure> Structure for Box<T> {
+    const KIND: &'static str = T::KIND;
+    fn hash_structure<H: Hasher>(&self, state: &mut H) {
+        self.as_ref().hash_structure(state)
+    }
+    fn compare_structure(&self, other: &Self) -> Comparison {
+        self.as_ref().compare_structure(other)
+    }
+}
+
+impl<T: Structure> Structure for Option<T> {
+    const KIND: &'static str = T::KIND;
+    fn hash_structure<H: Hasher>(&self, state: &mut H) {
+        self.is_some().hash(state);
+        if let Some(t) = self {
+            t.hash_structure(state)
+        }
+    }
+    fn compare_structure(&self, other: &Self) -> Comparison {
+        match (self, other) {
+            (Some(a), Some(b)) => a.compare_structure(b),
+            (None, None) => Comparison::Same,
+            _ => Comparison::Differ,
+        }
+    }
+}
+
+impl<T: Structure> Structure for Vec<T> {
+    const KIND: &'static str = T::KIND;
+    fn hash_structure<H: Hasher>(&self, state: &mut H) {
+        self.len().hash(state);
+        for t in self {
+            t.hash_structure(state)
+        }
+    }
+    fn compare_structure(&self, other: &Self) -> Comparison {
+        if self.len() != other.len() {
+            return Comparison::Differ;
+        }
+        self.iter()
+            .zip(other)
+            .map(|(a, b)| a.compare_structure(b))
+            .find(|comparison| *comparison != Comparison::Same)
+            .unwrap_or(Comparison::Same)
+    }
+}
+
+/// Nodes without children, which compare as they are
+macro_rules! leaf {
+    ($($t: ty => $kind: literal),* $(,)?) => {
+        $(
+            impl Structure for $t {
+                const KIND: &'static str = $kind;
+                fn hash_structure<H: Hasher>(&self, state: &mut H) {
+                    self.hash(state)
+                }
+                fn compare_structure(&self, other: &Self) -> Comparison {
+                    if self == other {
+                        Comparison::Same
+                    } else {
+                        Comparison::Differ
+                    }
+                }
+            }
+        )*
+    };
+}
+
+leaf!(String => "identifier", bool => "flag", Primitive => "primitive");
+
+impl Structure for Datum {
+    const KIND: &'static str = "datum";
+    fn hash_structure<H: Hasher>(&self, state: &mut H) {
+        discriminant(self).hash(state);
+        match self {
+            Datum::Error => {}
+            Datum::Keyword(keyword) => keyword.hash(state),
+            Datum::Primitive(primitive) => primitive.hash(state),
+            Datum::List(list) => list.hash_structure(state),
+        }
+    }
+    fn compare_structure(&self, other: &Self) -> Comparison {
+        match (self, other) {
+            (Datum::List(a), Datum::List(b)) => a.compare_structure(b),
+            (Datum::Primitive(a), Datum::Primitive(b)) => a.compare_structure(b),
+            (a, b) if a == b => Comparison::Same,
+            _ => Comparison::Differ,
+        }
+    }
+}
+
+impl Structure for Program {
+    const KIND: &'static str = "program";
+    fn hash_structure<H: Hasher>(&self, state: &mut H) {
+        self.libraries.hash_structure(state);
+        self.imports.hash_structure(state);
+        self.body.hash_structure(state);
+    }
+    fn compare_structure(&self, other: &Self) -> Comparison {
+        self.libraries
+            .compare_structure(&other.libraries)
+            .then(|| self.imports.compare_structure(&other.imports))
+            .then(|| self.body.compare_structure(&other.body))
+    }
+}
+
+impl Structure for ProcedureBody {
+    const KIND: &'static str = "body";
+    fn hash_structure<H: Hasher>(&self, state: &mut H) {
+        self.defs.hash_structure(state);
+        self.exprs.hash_structure(state);
+        self.last_expr.hash_structure(state);
+    }
+    fn compare_structure(&self, other: &Self) -> Comparison {
+        self.defs
+            .compare_structure(&other.defs)
+            .then(|| self.exprs.compare_structure(&other.exprs))
+            .then(|| self.last_expr.compare_structure(&other.last_expr))
+    }
+}
+
+impl Structure for Library {
+    const KIND: &'static str = "library";
+    fn hash_structure<H: Hasher>(&self, state: &mut H) {
+        self.name.hash_structure(state);
+        self.declarations.hash_structure(state);
+    }
+    fn compare_structure(&self, other: &Self) -> Comparison {
+        self.name
+            .compare_structure(&other.name)
+            .then(|| self.declarations.compare_structure(&other.declarations))
+    }
+}
+
+impl Structure for LibraryDeclaration {
+    const KIND: &'static str = "library declaration";
+    fn hash_structure<H: Hasher>(&self, state: &mut H) {
+        discriminant(self).hash(state);
+        match self {
+            LibraryDeclaration::Export(specs) => specs.hash_structure(state),
+            LibraryDeclaration::Import(sets) => sets.hash_structure(state),
+            LibraryDeclaration::Begin { defs, exprs } => {
+                defs.hash_structure(state);
+                exprs.hash_structure(state);
+            }
+            LibraryDeclaration::Include {
+                case_insensitive,
+                files,
+            } => {
+                case_insensitive.hash(state);
+                files.hash_structure(state);
+            }
+            LibraryDeclaration::IncludeLibraryDeclarations(files) => files.hash_structure(state),
+        }
+    }
+    fn compare_structure(&self, other: &Self) -> Comparison {
+        use LibraryDeclaration::*;
+        match (self, other) {
+            (Export(a), Export(b)) => a.compare_structure(b),
+            (Import(a), Import(b)) => a.compare_structure(b),
+            (
+                Begin { defs, exprs },
+                Begin {
+                    defs: other_defs,
+                    exprs: other_exprs,
+                },
+            ) => defs
+                .compare_structure(other_defs)
+                .then(|| exprs.compare_structure(other_exprs)),
+            (
+                Include {
+                    case_insensitive,
+                    files,
+                },
+                Include {
+                    case_insensitive: other_case_insensitive,
+                    files: other_files,
+                },
+            ) => case_insensitive
+                .compare_structure(other_case_insensitive)
+                .then(|| files.compare_structure(other_files)),
+            (IncludeLibraryDeclarations(a), IncludeLibraryDeclarations(b)) => {
+                a.compare_structure(b)
+            }
+            _ => Comparison::Differ,
+        }
+    }
+}
+
+impl Structure for ExportSpec {
+    const KIND: &'static str = "export spec";
+    fn hash_structure<H: Hasher>(&self, state: &mut H) {
+        discriminant(self).hash(state);
+        match self {
+            ExportSpec::Ident(ident) => ident.hash(state),
+            ExportSpec::Rename(rename) => rename.hash_structure(state),
+        }
+    }
+    fn compare_structure(&self, other: &Self) -> Comparison {
+        match (self, other) {
+            (ExportSpec::Ident(a), ExportSpec::Ident(b)) => a.compare_structure(b),
+            (ExportSpec::Rename(a), ExportSpec::Rename(b)) => a.compare_structure(b),
+            _ => Comparison::Differ,
+        }
+    }
+}
+
+impl Structure for ImportSet {
+    const KIND: &'static str = "import set";
+    fn hash_structure<H: Hasher>(&self, state: &mut H) {
+        discriminant(self).hash(state);
+        match self {
+            ImportSet::Library(name) => name.hash_structure(state),
+            ImportSet::Only(set, idents) | ImportSet::Except(set, idents) => {
+                set.hash_structure(state);
+                idents.hash_structure(state);
+            }
+            ImportSet::Prefix(set, prefix) => {
+                set.hash_structure(state);
+                prefix.hash_structure(state);
+            }
+            ImportSet::Rename(set, renames) => {
+                set.hash_structure(state);
+                renames.hash_structure(state);
+            }
+        }
+    }
+    fn compare_structure(&self, other: &Self) -> Comparison {
+        use ImportSet::*;
+        match (self, other) {
+            (Library(a), Library(b)) => a.compare_structure(b),
+            (Only(a, a_idents), Only(b, b_idents)) | (Except(a, a_idents), Except(b, b_idents)) => {
+                a.compare_structure(b)
+                    .then(|| a_idents.compare_structure(b_idents))
+            }
+            (Prefix(a, a_prefix), Prefix(b, b_prefix)) => a
+                .compare_structure(b)
+                .then(|| a_prefix.compare_structure(b_prefix)),
+            (Rename(a, a_renames), Rename(b, b_renames)) => a
+                .compare_structure(b)
+                .then(|| a_renames.compare_structure(b_renames)),
+            _ => Comparison::Differ,
+        }
+    }
+}
+
+impl Structure for Rename {
+    const KIND: &'static str = "rename";
+    fn hash_structure<H: Hasher>(&self, state: &mut H) {
+        self.0.hash_structure(state);
+        self.1.hash_structure(state);
+    }
+    fn compare_structure(&self, other: &Self) -> Comparison {
+        self.0
+            .compare_structure(&other.0)
+            .then(|| self.1.compare_structure(&other.1))
+    }
+}
+
+impl Structure for Definition {
+    const KIND: &'static str = "definition";
+    fn hash_structure<H: Hasher>(&self, state: &mut H) {
+        discriminant(self).hash(state);
+        match self {
+            Definition::Variable(name, expr) => {
+                name.hash_structure(state);
+                expr.hash_structure(state);
+            }
+            Definition::Values(formals, expr) => {
+                formals.hash_structure(state);
+                expr.hash_structure(state);
+            }
+            Definition::Begin(defs) => defs.hash_structure(state),
+        }
+    }
+    fn compare_structure(&self, other: &Self) -> Comparison {
+        use Definition::*;
+        match (self, other) {
+            (Variable(a, a_expr), Variable(b, b_expr)) => a
+                .compare_structure(b)
+                .then(|| a_expr.compare_structure(b_expr)),
+            (Values(a, a_expr), Values(b, b_expr)) => a
+                .compare_structure(b)
+                .then(|| a_expr.compare_structure(b_expr)),
+            (Begin(a), Begin(b)) => a.compare_structure(b),
+            _ => Comparison::Differ,
+        }
+    }
+}
+
+impl Structure for Formals {
+    const KIND: &'static str = "formals";
+    fn hash_structure<H: Hasher>(&self, state: &mut H) {
+        self.args.hash_structure(state);
+        self.rest.hash_structure(state);
+    }
+    fn compare_structure(&self, other: &Self) -> Comparison {
+        self.args
+            .compare_structure(&other.args)
+            .then(|| self.rest.compare_structure(&other.rest))
+    }
+}
+
+impl Structure for ValuesBinding {
+    const KIND: &'static str = "binding";
+    fn hash_structure<H: Hasher>(&self, state: &mut H) {
+        self.0.hash_structure(state);
+        self.1.hash_structure(state);
+    }
+    fn compare_structure(&self, other: &Self) -> Comparison {
+        self.0
+            .compare_structure(&other.0)
+            .then(|| self.1.compare_structure(&other.1))
+    }
+}
+
+impl Structure for Expression {
+    const KIND: &'static str = "expression";
+    fn hash_structure<H: Hasher>(&self, state: &mut H) {
+        discriminant(self).hash(state);
+        match self {
+            Expression::ProcedureCall { operator, args } => {
+                operator.hash_structure(state);
+                args.hash_structure(state);
+            }
+            Expression::Primitive(primitive) => primitive.hash(state),
+            Expression::Quote(datum) => datum.hash_structure(state),
+            Expression::Procedure { args, rest, body } => {
+                args.hash_structure(state);
+                rest.hash_structure(state);
+                body.hash_structure(state);
+            }
+            Expression::Conditional {
+                test,
+                conseq,
+                alter,
+            } => {
+                test.hash_structure(state);
+                conseq.hash_structure(state);
+                alter.hash_structure(state);
+            }
+            Expression::Assignment(name, expr) => {
+                name.hash_structure(state);
+                expr.hash_structure(state);
+            }
+            Expression::LetValues {
+                sequential,
+                bindings,
+                body,
+            } => {
+                sequential.hash(state);
+                bindings.hash_structure(state);
+                body.hash_structure(state);
+            }
+            Expression::Receive {
+                formals,
+                expr,
+                body,
+            } => {
+                formals.hash_structure(state);
+                expr.hash_structure(state);
+                body.hash_structure(state);
+            }
+            Expression::Begin { defs, exprs } => {
+                defs.hash_structure(state);
+                exprs.hash_structure(state);
+            }
+            Expression::Guard { var, clauses, body } => {
+                var.hash_structure(state);
+                clauses.hash_structure(state);
+                body.hash_structure(state);
+            }
+            Expression::Error => {}
+        }
+    }
+    fn compare_structure(&self, other: &Self) -> Comparison {
+        use Expression::*;
+        match (self, other) {
+            (
+                ProcedureCall { operator, args },
+                ProcedureCall {
+                    operator: other_operator,
+                    args: other_args,
+                },
+            ) => operator
+                .compare_structure(other_operator)
+                .then(|| args.compare_structure(other_args)),
+            (Primitive(a), Primitive(b)) => a.compare_structure(b),
+            (Quote(a), Quote(b)) => a.compare_structure(b),
+            (
+                Procedure { args, rest, body },
+                Procedure {
+                    args: other_args,
+                    rest: other_rest,
+                    body: other_body,
+                },
+            ) => args
+                .compare_structure(other_args)
+                .then(|| rest.compare_structure(other_rest))
+                .then(|| body.compare_structure(other_body)),
+            (
+                Conditional {
+                    test,
+                    conseq,
+                    alter,
+                },
+                Conditional {
+                    test: other_test,
+                    conseq: other_conseq,
+                    alter: other_alter,
+                },
+            ) => test
+                .compare_structure(other_test)
+                .then(|| conseq.compare_structure(other_conseq))
+                .then(|| alter.compare_structure(other_alter)),
+            (Assignment(a, a_expr), Assignment(b, b_expr)) => a
+                .compare_structure(b)
+                .then(|| a_expr.compare_structure(b_expr)),
+            (
+                LetValues {
+                    sequential,
+                    bindings,
+                    body,
+                },
+                LetValues {
+                    sequential: other_sequential,
+                    bindings: other_bindings,
+                    body: other_body,
+                },
+            ) => sequential
+                .compare_structure(other_sequential)
+                .then(|| bindings.compare_structure(other_bindings))
+                .then(|| body.compare_structure(other_body)),
+            (
+                Receive {
+                    formals,
+                    expr,
+                    body,
+                },
+                Receive {
+                    formals: other_formals,
+                    expr: other_expr,
+                    body: other_body,
+                },
+            ) => formals
+                .compare_structure(other_formals)
+                .then(|| expr.compare_structure(other_expr))
+                .then(|| body.compare_structure(other_body)),
+            (
+                Begin { defs, exprs },
+                Begin {
+                    defs: other_defs,
+                    exprs: other_exprs,
+                },
+            ) => defs
+                .compare_structure(other_defs)
+                .then(|| exprs.compare_structure(other_exprs)),
+            (
+                Guard { var, clauses, body },
+                Guard {
+                    var: other_var,
+                    clauses: other_clauses,
+                    body: other_body,
+                },
+            ) => var
+                .compare_structure(other_var)
+                .then(|| clauses.compare_structure(other_clauses))
+                .then(|| body.compare_structure(other_body)),
+            (Error, Error) => Comparison::Same,
+            _ => Comparison::Differ,
+        }
+    }
+}
+
+impl Structure for CondClause {
+    const KIND: &'static str = "clause";
+    fn hash_structure<H: Hasher>(&self, state: &mut H) {
+        discriminant(self).hash(state);
+        match self {
+            CondClause::Test { test, body } => {
+                test.hash_structure(state);
+                body.hash_structure(state);
+            }
+            CondClause::Arrow { test, receiver } => {
+                test.hash_structure(state);
+                receiver.hash_structure(state);
+            }
+            CondClause::Else(body) => body.hash_structure(state),
+        }
+    }
+    fn compare_structure(&self, other: &Self) -> Comparison {
+        use CondClause::*;
+        match (self, other) {
+            (
+                Test { test, body },
+                Test {
+                    test: other_test,
+                    body: other_body,
+                },
+            ) => test
+                .compare_structure(other_test)
+                .then(|| body.compare_structure(other_body)),
+            (
+                Arrow { test, receiver },
+                Arrow {
+                    test: other_test,
+                    receiver: other_receiver,
+                },
+            ) => test
+                .compare_structure(other_test)
+                .then(|| receiver.compare_structure(other_receiver)),
+            (Else(a), Else(b)) => a.compare_structure(b),
+            _ => Comparison::Differ,
+        }
+    }
+}
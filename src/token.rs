@@ -1,7 +1,7 @@
 use std::fmt::Display;
 
 use derive_more::From;
-use num::{rational::Ratio, traits::Pow, BigInt, Integer, ToPrimitive};
+use num::{rational::Ratio, traits::Pow, BigInt, Signed, ToPrimitive};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Real {
@@ -32,9 +32,19 @@ impl ToPrimitive for Real {
 impl Display for Real {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let denom = self.denom();
-        let int = self.numer.div_floor(&denom);
-        let frac = self.numer.mod_floor(&denom);
-        write!(f, "{}.{}", int, frac)
+        let numer = self.numer.abs();
+        let sign = if self.numer.is_negative() { "-" } else { "" };
+        // The leading zeros of the fraction are counted by `denom_log10`
+        let frac = (&numer % &denom).to_string();
+        let width = (self.denom_log10 as usize).max(1);
+        write!(
+            f,
+            "{}{}.{:0>width$}",
+            sign,
+            numer / denom,
+            frac,
+            width = width
+        )
     }
 }
 
@@ -44,6 +54,8 @@ pub enum Primitive {
     Bool(bool),
     Real(Real),
     Ident(String),
+    #[from(ignore)]
+    String(String),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, From)]
@@ -68,6 +80,21 @@ impl Display for Primitive {
             Primitive::Real(real) => {
                 write!(f, "{}", real)
             }
+            Primitive::String(s) => {
+                write!(f, "\"")?;
+                for c in s.chars() {
+                    match c {
+                        '"' => write!(f, "\\\"")?,
+                        '\\' => write!(f, "\\\\")?,
+                        '\n' => write!(f, "\\n")?,
+                        '\t' => write!(f, "\\t")?,
+                        '\r' => write!(f, "\\r")?,
+                        c if c.is_control() => write!(f, "\\x{:x};", c as u32)?,
+                        c => write!(f, "{}", c)?,
+                    }
+                }
+                write!(f, "\"")
+            }
         }
     }
 }
@@ -1,92 +1,502 @@
-pub mod builtin;
+pub mod cond_expand;
+pub mod include;
+pub mod pattern;
 use ::chumsky::prelude::*;
 use chumsky::combinator::MapWithSpan;
-use std::{hash::Hash, ops::Range};
+use std::{collections::HashSet, hash::Hash, ops::Range};
 
-use crate::{Datum, IntoTokens, ParseError, Spanned, Token};
+use self::{
+    cond_expand::{CondExpand, Features},
+    include::Includer,
+};
+use crate::{
+    abbreviation, Datum, Expansions, ParseError, Primitive, SourceLoader, SourceMap, Span, Spanned,
+    Token, TokenizeOrParseError,
+};
 pub trait Transformer {
-    fn transform(&self, datum: &mut Spanned<Datum>);
+    /// Rewrite `datum` if it is a use of this transformer, returns whether it is rewritten.
+    ///
+    /// Returning an error replaces `datum` with `Datum::Error`, errors reported through `context`
+    /// leave `datum` to the transformer. Either way, the expansion goes on to report more errors.
+    fn transform(
+        &self,
+        datum: &mut Spanned<Datum>,
+        context: &mut Context,
+    ) -> Result<bool, Box<Simple<Token>>>;
 }
 
+/// What a `Transformer` could know about and report to the parse
+pub struct Context<'a> {
+    source_path: &'a str,
+    features: &'a Features,
+    pub(crate) errors: Vec<Simple<Token>>,
+    pub(crate) warnings: Vec<Simple<Token>>,
+    pub(crate) expansions: Expansions,
+    symbols: usize,
+}
+
+impl<'a> Context<'a> {
+    pub fn new(source_path: &'a str, features: &'a Features) -> Self {
+        Self {
+            source_path,
+            features,
+            errors: Vec::new(),
+            warnings: Vec::new(),
+            expansions: Expansions::default(),
+            symbols: 0,
+        }
+    }
+    /// The path of the main source
+    pub fn source_path(&self) -> &str {
+        self.source_path
+    }
+    /// Features and libraries which `cond-expand` tests against
+    pub fn features(&self) -> &Features {
+        self.features
+    }
+    pub fn error(&mut self, error: Simple<Token>) {
+        self.errors.push(error);
+    }
+    pub fn warning(&mut self, warning: Simple<Token>) {
+        self.warnings.push(warning);
+    }
+    pub fn errors(&self) -> &[Simple<Token>] {
+        &self.errors
+    }
+    pub fn warnings(&self) -> &[Simple<Token>] {
+        &self.warnings
+    }
+    /// Expansions so far, the innermost last
+    pub fn expansions(&self) -> &Expansions {
+        &self.expansions
+    }
+    /// A fresh identifier starting with `prefix`, which is different from every identifier in the
+    /// source, as the lexer never puts `#` in an identifier
+    pub fn gensym(&mut self, prefix: &str) -> String {
+        self.symbols += 1;
+        format!("{}#{}", prefix, self.symbols)
+    }
+}
+
+/// How deep expansions could nest by default, an expansion nests in the one which produced it
+pub const DEFAULT_EXPANSION_LIMIT: usize = 128;
+
 fn enclosed<T>(
     parser: impl chumsky::Parser<Token, T, Error = Simple<Token>>,
 ) -> impl chumsky::Parser<Token, T, Error = Simple<Token>> {
     parser.delimited_by(just(Token::Keyword("(")), just(Token::Keyword(")")))
 }
+/// Pairs an output with its span
+type WithSpan<T> = fn(T, Range<usize>) -> (T, Range<usize>);
 fn spanned<S: Clone + Hash + Eq, T, I: chumsky::Parser<S, T, Error = Simple<S>>>(
     parser: I,
-) -> MapWithSpan<I, impl Fn(T, Range<usize>) -> (T, Range<usize>) + Clone + Copy, T> {
+) -> MapWithSpan<I, WithSpan<T>, T> {
     parser.map_with_span(|t, span| (t, span))
 }
-pub fn datums() -> impl Parser<Token, Vec<Spanned<Datum>>, Error = Simple<Token>> {
-    macro_rules! select {
-        ($category: literal, {$($p:pat $(if $guard:expr)? => $out:expr),+ $(,)?}) => ({
-            filter_map(move |span: std::ops::Range<usize>, x| match x {
-                $($p $(if $guard)? => ::core::result::Result::Ok($out)),+,
-                other => Err(Simple::expected_input_found(
-                    span,
-                    Some(Some(Token::Keyword($category))),
-                    Some(other),
-                )),
-            })
-        });
-    }
-    recursive(|datum| {
-            let keyword = select! {
-                "<keyword>",
-                {
-                    Token::Keyword(keyword)  if keyword != "(" && keyword != ")" => Datum::Keyword(keyword),
-                }
-            };
-            let primitive = select! {
-                "<primitive>",
-                {
-                    Token::Primitive(p) => Datum::Primitive(p),
-                }
-            };
-            let list = enclosed(datum.repeated())
-                .map(Datum::List)
-                .labelled("( ... )");
-            spanned(
-                list.or(keyword)
-                    .or(primitive)
-                    .recover_with(nested_delimiters(
-                        Token::Keyword("("),
-                        Token::Keyword(")"),
-                        [],
-                        |_| Datum::Error,
-                    )),
+/// A token which `select` accepts, or an error expecting `category`
+fn category<O>(
+    category: &'static str,
+    select: fn(&Token) -> Option<O>,
+) -> impl chumsky::Parser<Token, O, Error = Simple<Token>> + Clone {
+    filter(move |token| select(token).is_some())
+        .map(move |token| select(&token).unwrap())
+        .map_err(move |e: Simple<Token>| {
+            Simple::expected_input_found(
+                e.span(),
+                Some(Some(Token::Keyword(category))),
+                e.found().cloned(),
             )
         })
-        .repeated()
-        .then_ignore(end())
 }
+pub fn datums() -> impl Parser<Token, Vec<Spanned<Datum>>, Error = Simple<Token>> {
+    recursive(|datum| {
+        let keyword = category("<keyword>", |token| match token {
+            Token::Keyword(keyword)
+                if *keyword != "(" && *keyword != ")" && abbreviation(keyword).is_none() =>
+            {
+                Some(Datum::Keyword(keyword))
+            }
+            _ => None,
+        });
+        let prefix = category("<abbreviation>", |token| match token {
+            Token::Keyword(keyword) if abbreviation(keyword).is_some() => Some(*keyword),
+            _ => None,
+        });
+        // `'datum` is read as `(quote datum)`, and so on
+        let abbreviated = spanned(prefix)
+            .then(datum.clone())
+            .map(|((prefix, span), datum)| {
+                let name = abbreviation(prefix).unwrap();
+                Datum::List(vec![(Primitive::Ident(name.into()).into(), span), datum])
+            });
+        let primitive = category("<primitive>", |token| match token {
+            Token::Primitive(p) => Some(Datum::Primitive(p.clone())),
+            _ => None,
+        });
+        let list = enclosed(datum.repeated())
+            .map(Datum::List)
+            .labelled("( ... )");
+        spanned(
+            list.or(abbreviated)
+                .or(keyword)
+                .or(primitive)
+                .recover_with(nested_delimiters(
+                    Token::Keyword("("),
+                    Token::Keyword(")"),
+                    [],
+                    |_| Datum::Error,
+                )),
+        )
+    })
+    .repeated()
+    .then_ignore(end())
+}
+/// Datumize tokens whose end of input is at `eoi`
+pub(crate) fn datums_at(
+    tokens: &[Spanned<Token>],
+    eoi: usize,
+) -> Result<Vec<Spanned<Datum>>, Vec<Simple<Token>>> {
+    use ::chumsky::Parser as _;
+    datums().parse(::chumsky::Stream::from_iter(
+        eoi..eoi + 1,
+        tokens.iter().cloned(),
+    ))
+}
+
 pub fn datumize(
     tokens: &[Spanned<Token>],
     source: &str,
     source_path: &str,
 ) -> Result<Vec<Spanned<Datum>>, ParseError<Token>> {
-    use ::chumsky::Parser as _;
-    let len = source.len();
-    datums()
-        .parse(::chumsky::Stream::from_iter(
-            len..len + 1,
-            tokens.iter().cloned(),
-        ))
+    datums_at(tokens, source.len())
         .map_err(|e| ParseError::new(source.to_owned(), source_path.to_owned(), e, "token"))
 }
 
+/// `sources` should contain the main source, which `tokens` come from. The files of `include` are
+/// added to `sources` while expanding.
+///
+/// Only the builtins named in `builtins` run, they are tried in order before the transformers on
+/// every datum until none of them rewrites it. Errors of expanding are collected in `context`, to be reported
+/// with the errors of parsing.
 pub fn expansion(
-    transformers: &mut Vec<Box<dyn Transformer>>,
+    transformers: &[(String, Box<dyn Transformer>)],
+    builtins: &[&'static str],
+    loader: &dyn SourceLoader,
+    context: &mut Context,
+    limit: usize,
+    sources: &mut SourceMap,
     tokens: &[Spanned<Token>],
-    source: &str,
-    source_path: &str,
-) -> Result<Vec<Spanned<Token>>, ParseError<Token>> {
-    let mut datums = datumize(tokens, source, source_path)?;
-    for transformer in transformers {
-        for datum in &mut datums {
-            transformer.transform(datum);
+) -> Result<Vec<Spanned<Datum>>, TokenizeOrParseError> {
+    let main = sources.files()[0].clone();
+    let datums = datumize(tokens, &main.source, &main.path)?;
+    let features = context.features;
+    let mut expander = Expander {
+        transformers,
+        builtins,
+        context,
+        limit,
+        cond_expand: CondExpand { features },
+        includer: Includer::new(loader, sources),
+    };
+    expander.sequence(datums, Position::Body, 0)
+}
+
+/// Where a datum is, which decides how the datums of `cond-expand` and `include` take its place
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Position {
+    /// In the top level, a body or a `begin`, where the datums are spliced
+    Body,
+    /// A declaration of `define-library`, where `include` is a declaration of its own
+    Declaration,
+    /// Where one datum is expected, the datums are wrapped in `begin`
+    Expression,
+}
+
+/// Where the elements of `list` are in a body or declarations, from which index on
+fn sequence(list: &[Spanned<Datum>]) -> Option<(usize, Position)> {
+    let head = match list.first() {
+        Some((Datum::Keyword(keyword), _)) => *keyword,
+        Some((Datum::Primitive(Primitive::Ident(ident)), _)) => ident.as_str(),
+        _ => return None,
+    };
+    match head {
+        "begin" => Some((1, Position::Body)),
+        "lambda" | "let-values" | "let*-values" | "guard" => Some((2, Position::Body)),
+        "define" if matches!(list.get(1), Some((Datum::List(_), _))) => Some((2, Position::Body)),
+        "receive" => Some((3, Position::Body)),
+        "define-library" => Some((2, Position::Declaration)),
+        _ => None,
+    }
+}
+
+/// `(quote datum)`, whose datum is not code
+fn is_quote(list: &[Spanned<Datum>]) -> bool {
+    matches!(list, [(Datum::Primitive(Primitive::Ident(head)), _), _] if head == "quote")
+}
+
+/// Datums which a builtin expands into
+struct Expanded {
+    name: &'static str,
+    /// Span of the keyword, such as `include`
+    keyword: Span,
+    /// Datums of the included files by their paths, or of the selected `cond-expand` clause
+    groups: Vec<(Option<String>, Vec<Spanned<Datum>>)>,
+}
+
+/// Expands the builtins and the transformers through the datum tree, but not in quoted data.
+/// `depth` is the number of expansions which a datum is nested in.
+struct Expander<'a, 'b> {
+    transformers: &'a [(String, Box<dyn Transformer>)],
+    builtins: &'a [&'static str],
+    context: &'a mut Context<'b>,
+    limit: usize,
+    cond_expand: CondExpand<'b>,
+    includer: Includer<'a>,
+}
+
+impl Expander<'_, '_> {
+    fn sequence(
+        &mut self,
+        datums: Vec<Spanned<Datum>>,
+        position: Position,
+        depth: usize,
+    ) -> Result<Vec<Spanned<Datum>>, TokenizeOrParseError> {
+        let mut expanded = Vec::with_capacity(datums.len());
+        for datum in datums {
+            self.splice(datum, position, depth, &mut expanded)?;
+        }
+        Ok(expanded)
+    }
+
+    /// Expand `datum` into any number of datums in `expanded`
+    fn splice(
+        &mut self,
+        mut datum: Spanned<Datum>,
+        position: Position,
+        mut depth: usize,
+        expanded: &mut Vec<Spanned<Datum>>,
+    ) -> Result<(), TokenizeOrParseError> {
+        match self.rewrite(&mut datum, position, &mut depth)? {
+            Some(Expanded { groups, .. }) => self.groups(groups, position, depth, expanded),
+            None => {
+                self.nested(&mut datum, depth)?;
+                expanded.push(datum);
+                Ok(())
+            }
+        }
+    }
+
+    /// Expand `datum` where one datum is expected
+    fn expand(
+        &mut self,
+        datum: &mut Spanned<Datum>,
+        mut depth: usize,
+    ) -> Result<(), TokenizeOrParseError> {
+        match self.rewrite(datum, Position::Expression, &mut depth)? {
+            Some(Expanded { groups, name, .. })
+                if groups.iter().all(|(_, datums)| datums.is_empty()) =>
+            {
+                self.context.error(Simple::custom(
+                    datum.1.clone(),
+                    format!(
+                        "`{}` expands to nothing where an expression is expected",
+                        name
+                    ),
+                ));
+                datum.0 = Datum::Error;
+                Ok(())
+            }
+            Some(Expanded {
+                keyword, groups, ..
+            }) => {
+                let mut list = vec![(Datum::Primitive(Primitive::Ident("begin".into())), keyword)];
+                self.groups(groups, Position::Body, depth, &mut list)?;
+                datum.0 = Datum::List(list);
+                Ok(())
+            }
+            None => self.nested(datum, depth),
+        }
+    }
+
+    fn groups(
+        &mut self,
+        groups: Vec<(Option<String>, Vec<Spanned<Datum>>)>,
+        position: Position,
+        depth: usize,
+        expanded: &mut Vec<Spanned<Datum>>,
+    ) -> Result<(), TokenizeOrParseError> {
+        for (path, datums) in groups {
+            if let Some(path) = &path {
+                self.includer.enter(path);
+            }
+            for datum in datums {
+                self.splice(datum, position, depth, expanded)?;
+            }
+            if path.is_some() {
+                self.includer.leave();
+            }
+        }
+        Ok(())
+    }
+
+    /// Expand the datums nested in `datum`
+    fn nested(
+        &mut self,
+        datum: &mut Spanned<Datum>,
+        depth: usize,
+    ) -> Result<(), TokenizeOrParseError> {
+        let Datum::List(list) = &mut datum.0 else {
+            return Ok(());
+        };
+        if is_quote(list) {
+            return Ok(());
+        }
+        let (start, position) = sequence(list).unwrap_or((list.len(), Position::Expression));
+        let mut items = std::mem::take(list).into_iter();
+        let mut expanded = items.by_ref().take(start).collect::<Vec<_>>();
+        for item in &mut expanded {
+            self.expand(item, depth)?;
+        }
+        for item in items {
+            self.splice(item, position, depth, &mut expanded)?;
+        }
+        *list = expanded;
+        Ok(())
+    }
+
+    /// Rewrite `datum` with the transformers until none of them rewrites it, or a builtin expands
+    /// it into the returned datums
+    fn rewrite(
+        &mut self,
+        datum: &mut Spanned<Datum>,
+        position: Position,
+        depth: &mut usize,
+    ) -> Result<Option<Expanded>, TokenizeOrParseError> {
+        loop {
+            let expanded = self.builtin(datum, position)?;
+            if expanded.is_none() && !self.transform(datum) {
+                return Ok(None);
+            }
+            *depth += 1;
+            if *depth > self.limit {
+                self.context.error(Simple::custom(
+                    datum.1.clone(),
+                    format!(
+                        "Expansion exceeds the depth limit of {}, does a transformer expand into itself?",
+                        self.limit
+                    ),
+                ));
+                datum.0 = Datum::Error;
+                return Ok(None);
+            }
+            if expanded.is_some() {
+                return Ok(expanded);
+            }
+        }
+    }
+
+    /// Try every transformer on `datum` once, returns whether any of them rewrote it
+    fn transform(&mut self, datum: &mut Spanned<Datum>) -> bool {
+        let mut rewritten = false;
+        let mut nested = nested_spans(datum);
+        for (name, transformer) in self.transformers {
+            let call_site = datum.1.clone();
+            match transformer.transform(datum, self.context) {
+                Ok(true) => {
+                    rewritten = true;
+                    let (mut generated, mut moved) = (vec![datum.1.clone()], Vec::new());
+                    if let Datum::List(list) = &datum.0 {
+                        for datum in list {
+                            partition_spans(datum, &nested, &mut generated, &mut moved);
+                        }
+                    }
+                    self.context
+                        .expansions
+                        .add(name, call_site, generated, moved);
+                    nested = nested_spans(datum);
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    self.context.error(*e);
+                    datum.0 = Datum::Error;
+                    return false;
+                }
+            }
+        }
+        rewritten
+    }
+
+    fn builtin(
+        &mut self,
+        datum: &Spanned<Datum>,
+        position: Position,
+    ) -> Result<Option<Expanded>, TokenizeOrParseError> {
+        let keyword = match &datum.0 {
+            Datum::List(list) => match list.first() {
+                Some((_, span)) => span.clone(),
+                None => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+        for &name in self.builtins {
+            let groups = match name {
+                "cond-expand" => self
+                    .cond_expand
+                    .expand(datum, &mut self.context.errors)
+                    .map(|datums| vec![(None, datums)]),
+                "include" if position != Position::Declaration => self
+                    .includer
+                    .include(datum, &mut self.context.errors)?
+                    .map(|files| {
+                        files
+                            .into_iter()
+                            .map(|file| (file.path, file.datums))
+                            .collect()
+                    }),
+                _ => None,
+            };
+            if let Some(groups) = groups {
+                return Ok(Some(Expanded {
+                    name,
+                    keyword,
+                    groups,
+                }));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Spans of the datums nested in `datum`
+fn nested_spans(datum: &Spanned<Datum>) -> HashSet<Span> {
+    fn collect(datum: &Spanned<Datum>, spans: &mut HashSet<Span>) {
+        if let Datum::List(list) = &datum.0 {
+            for datum in list {
+                spans.insert(datum.1.clone());
+                collect(datum, spans);
+            }
+        }
+    }
+    let mut spans = HashSet::new();
+    collect(datum, &mut spans);
+    spans
+}
+
+/// Tell the datums moved from the call site, whose spans are in `nested`, from the generated ones
+fn partition_spans(
+    datum: &Spanned<Datum>,
+    nested: &HashSet<Span>,
+    generated: &mut Vec<Span>,
+    moved: &mut Vec<Span>,
+) {
+    if nested.contains(&datum.1) {
+        moved.push(datum.1.clone());
+        return;
+    }
+    generated.push(datum.1.clone());
+    if let Datum::List(list) = &datum.0 {
+        for datum in list {
+            partition_spans(datum, nested, generated, moved);
         }
     }
-    Ok(datums.into_tokens())
 }
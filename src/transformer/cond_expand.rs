@@ -0,0 +1,161 @@
+use std::collections::HashSet;
+
+use ::chumsky::prelude::Simple;
+
+use crate::*;
+
+/// Features and libraries which `cond-expand` tests against
+#[derive(Debug, Clone, Default)]
+pub struct Features {
+    features: HashSet<String>,
+    libraries: HashSet<Vec<String>>,
+}
+
+impl Features {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_feature(mut self, feature: &str) -> Self {
+        self.features.insert(feature.to_owned());
+        self
+    }
+    /// `name` is the parts of a library name, such as `["scheme", "base"]`
+    pub fn with_library(mut self, name: &[&str]) -> Self {
+        self.libraries
+            .insert(name.iter().map(|part| part.to_string()).collect());
+        self
+    }
+    pub fn has_feature(&self, feature: &str) -> bool {
+        self.features.contains(feature)
+    }
+    pub fn has_library(&self, name: &[String]) -> bool {
+        self.libraries.contains(name)
+    }
+}
+
+/// Expand `(cond-expand (requirement datum ...) ...)` into the datums of the first clause whose
+/// requirement is satisfied. The expansion splices them into a body or library declarations like
+/// the contents of `begin`, and wraps them in `begin` where an expression is expected. Nothing is
+/// spliced if no clause is satisfied.
+pub struct CondExpand<'a> {
+    pub features: &'a Features,
+}
+
+impl<'a> CondExpand<'a> {
+    /// The datums of the satisfied clause if `datum` is a `cond-expand`, errors are pushed to
+    /// `errors`
+    pub fn expand(
+        &self,
+        (datum, span): &Spanned<Datum>,
+        errors: &mut Vec<Simple<Token>>,
+    ) -> Option<Vec<Spanned<Datum>>> {
+        let clauses = cond_expand_clauses(datum)?;
+        Some(self.select(clauses, span, errors))
+    }
+
+    /// Datums of the first satisfied clause
+    fn select(
+        &self,
+        clauses: &[Spanned<Datum>],
+        span: &Span,
+        errors: &mut Vec<Simple<Token>>,
+    ) -> Vec<Spanned<Datum>> {
+        if clauses.is_empty() {
+            errors.push(Simple::custom(
+                span.clone(),
+                "Expect at least one cond-expand clause",
+            ));
+        }
+        for (i, (clause, clause_span)) in clauses.iter().enumerate() {
+            let (requirement, body) = match clause {
+                Datum::List(list) if !list.is_empty() => (&list[0], &list[1..]),
+                _ => {
+                    errors.push(Simple::custom(
+                        clause_span.clone(),
+                        format!("Expect a cond-expand clause, found {}", clause.summary()),
+                    ));
+                    return Vec::new();
+                }
+            };
+            let satisfied = if is_ident(&requirement.0, "else") {
+                if i + 1 != clauses.len() {
+                    errors.push(else_not_last(clause_span.clone(), "cond-expand"));
+                    return Vec::new();
+                }
+                true
+            } else {
+                match self.requirement(requirement) {
+                    Ok(satisfied) => satisfied,
+                    Err(e) => {
+                        errors.push(*e);
+                        return Vec::new();
+                    }
+                }
+            };
+            if satisfied {
+                return body.to_vec();
+            }
+        }
+        Vec::new()
+    }
+
+    fn requirement(&self, (datum, span): &Spanned<Datum>) -> Result<bool, Box<Simple<Token>>> {
+        let invalid = || {
+            Box::new(Simple::custom(
+                span.clone(),
+                format!("Invalid feature requirement {}", datum.summary()),
+            ))
+        };
+        match datum {
+            Datum::Primitive(Primitive::Ident(feature)) => Ok(self.features.has_feature(feature)),
+            Datum::List(list) => match list.split_first() {
+                Some(((head, _), requirements)) if is_ident(head, "and") => {
+                    let mut satisfied = true;
+                    for requirement in requirements {
+                        satisfied &= self.requirement(requirement)?;
+                    }
+                    Ok(satisfied)
+                }
+                Some(((head, _), requirements)) if is_ident(head, "or") => {
+                    let mut satisfied = false;
+                    for requirement in requirements {
+                        satisfied |= self.requirement(requirement)?;
+                    }
+                    Ok(satisfied)
+                }
+                Some(((head, _), [requirement])) if is_ident(head, "not") => {
+                    Ok(!self.requirement(requirement)?)
+                }
+                Some(((head, _), [(Datum::List(name), _)])) if is_ident(head, "library") => {
+                    let name = name
+                        .iter()
+                        .map(|(part, _)| match part {
+                            Datum::Primitive(p @ (Primitive::Ident(_) | Primitive::Integer(_))) => {
+                                Ok(p.to_string())
+                            }
+                            _ => Err(invalid()),
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    Ok(self.features.has_library(&name))
+                }
+                _ => Err(invalid()),
+            },
+            _ => Err(invalid()),
+        }
+    }
+}
+
+fn is_ident(datum: &Datum, name: &str) -> bool {
+    matches!(datum, Datum::Primitive(Primitive::Ident(ident)) if ident == name)
+}
+
+/// The clauses if `datum` is a `cond-expand`
+fn cond_expand_clauses(datum: &Datum) -> Option<&[Spanned<Datum>]> {
+    match datum {
+        Datum::List(list) => match list.split_first() {
+            Some(((head, _), clauses)) if is_ident(head, "cond-expand") => Some(clauses),
+            _ => None,
+        },
+        _ => None,
+    }
+}
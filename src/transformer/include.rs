@@ -0,0 +1,153 @@
+use ::chumsky::prelude::Simple;
+
+use crate::{transformer::datums_at, *};
+
+/// Read the files of `(include file ...)` and `(include-ci file ...)` through a `SourceLoader`.
+/// The expansion splices the datums in place of the `include`, or wraps them in `begin` where an
+/// expression is expected.
+///
+/// Every file is added to the `SourceMap`, so the spans of the included datums tell which file they
+/// come from.
+pub struct Includer<'a> {
+    loader: &'a dyn SourceLoader,
+    sources: &'a mut SourceMap,
+    /// Files which are being included, to find include cycles
+    stack: Vec<String>,
+}
+
+/// Datums read from an included file
+#[derive(Debug)]
+pub struct Included {
+    /// `None` if the file could not be read, then the datums are an error in place of the file
+    pub path: Option<String>,
+    pub datums: Vec<Spanned<Datum>>,
+}
+
+impl Included {
+    fn error(span: Span) -> Self {
+        Self {
+            path: None,
+            datums: vec![(Datum::Error, span)],
+        }
+    }
+}
+
+impl<'a> Includer<'a> {
+    /// `sources` should contain the main source
+    pub fn new(loader: &'a dyn SourceLoader, sources: &'a mut SourceMap) -> Self {
+        let stack = sources
+            .files()
+            .first()
+            .map(|file| vec![file.path.clone()])
+            .unwrap_or_default();
+        Self {
+            loader,
+            sources,
+            stack,
+        }
+    }
+
+    /// The files of `datum` if it is an `include`, in the order they are named. Includes in the
+    /// datums of a file should be expanded between `enter` and `leave` of the file, so that they
+    /// are resolved relative to it and cycles are found.
+    ///
+    /// A file which could not be loaded or read is reported to `errors`, the expansion goes on
+    /// with the other files. Only a file which could not be tokenized fails the include.
+    pub fn include(
+        &mut self,
+        (datum, span): &Spanned<Datum>,
+        errors: &mut Vec<Simple<Token>>,
+    ) -> Result<Option<Vec<Included>>, TokenizeOrParseError> {
+        let (case_insensitive, files) = match datum {
+            Datum::List(list) => match list.split_first() {
+                Some(((Datum::Primitive(Primitive::Ident(head)), _), files))
+                    if head == "include" || head == "include-ci" =>
+                {
+                    (head == "include-ci", files)
+                }
+                _ => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+        if files.is_empty() {
+            errors.push(Simple::custom(
+                span.clone(),
+                "Expect at least one file to include",
+            ));
+            return Ok(Some(vec![Included::error(span.clone())]));
+        }
+        let mut included = Vec::new();
+        for (file, file_span) in files {
+            let file = match file {
+                Datum::Primitive(Primitive::String(file)) => file,
+                file => {
+                    errors.push(Simple::custom(
+                        file_span.clone(),
+                        format!(
+                            "Expect a string as the file to include, found {}",
+                            file.summary()
+                        ),
+                    ));
+                    included.push(Included::error(file_span.clone()));
+                    continue;
+                }
+            };
+            included.push(self.read(file, file_span.clone(), case_insensitive, errors)?);
+        }
+        Ok(Some(included))
+    }
+    pub fn enter(&mut self, path: &str) {
+        self.stack.push(path.to_owned());
+    }
+    pub fn leave(&mut self) {
+        self.stack.pop();
+    }
+
+    fn read(
+        &mut self,
+        file: &str,
+        span: Span,
+        case_insensitive: bool,
+        errors: &mut Vec<Simple<Token>>,
+    ) -> Result<Included, TokenizeOrParseError> {
+        let from = self.stack.last().cloned().unwrap_or_default();
+        let path = self.loader.resolve(&from, file);
+        if self.stack.contains(&path) {
+            let mut cycle = self.stack.clone();
+            cycle.push(path);
+            errors.push(Simple::custom(
+                span.clone(),
+                format!("Include cycle: {}", cycle.join(" -> ")),
+            ));
+            return Ok(Included::error(span));
+        }
+        let source = match self.loader.load(&path) {
+            Ok(source) => source,
+            Err(e) => {
+                errors.push(Simple::custom(
+                    span.clone(),
+                    format!("Failed to load '{}': {}", path, e),
+                ));
+                return Ok(Included::error(span));
+            }
+        };
+        let offset = self.sources.add(&path, &source);
+        let mut tokens = tokenize(&source, &path)?;
+        for (_, span) in &mut tokens {
+            *span = span.start + offset..span.end + offset;
+        }
+        if case_insensitive {
+            fold_case(&mut tokens);
+        }
+        match datums_at(&tokens, offset + source.len()) {
+            Ok(datums) => Ok(Included {
+                path: Some(path),
+                datums,
+            }),
+            Err(e) => {
+                errors.extend(e);
+                Ok(Included::error(span))
+            }
+        }
+    }
+}
@@ -0,0 +1,380 @@
+use std::collections::HashMap;
+
+use ::chumsky::prelude::Simple;
+
+use crate::{
+    transformer::{datums_at, Context, Transformer},
+    *,
+};
+
+/// A `&'static Pattern` read only once, optionally followed by the literals in brackets:
+/// `datum_pattern!("(cond (test body ...) ...)", ["else"])`. Panics if the pattern is invalid.
+#[macro_export]
+macro_rules! datum_pattern {
+    ($pattern: literal $(, [$($literal: literal),* $(,)?])?) => {{
+        static PATTERN: ::std::sync::OnceLock<$crate::transformer::pattern::Pattern> =
+            ::std::sync::OnceLock::new();
+        PATTERN.get_or_init(|| {
+            $crate::transformer::pattern::Pattern::with_literals($pattern, &[$($($literal),*)?])
+                .unwrap_or_else(|e| panic!("{}", e))
+        })
+    }};
+}
+
+/// A `&'static Template` read only once: `datum_template!("((lambda (tmp) tmp) expr)")`
+#[macro_export]
+macro_rules! datum_template {
+    ($template: literal) => {{
+        static TEMPLATE: ::std::sync::OnceLock<$crate::transformer::pattern::Template> =
+            ::std::sync::OnceLock::new();
+        TEMPLATE.get_or_init(|| $crate::transformer::pattern::Template::new($template))
+    }};
+}
+
+const ELLIPSIS: &str = "...";
+
+/// Read the only datum of `source`, which is written by the author of a transformer
+fn read(source: &str) -> Spanned<Datum> {
+    let tokens = tokenize(source, "<pattern>").unwrap_or_else(|e| panic!("{}", e));
+    let mut datums = datums_at(&tokens, source.len()).unwrap_or_else(|e| {
+        panic!(
+            "{}",
+            ParseError::new(source.to_owned(), "<pattern>".to_owned(), e, "token")
+        )
+    });
+    assert_eq!(datums.len(), 1, "Expect exactly one datum in {:?}", source);
+    datums.remove(0)
+}
+
+fn ident(datum: &Datum) -> Option<&str> {
+    match datum {
+        Datum::Primitive(Primitive::Ident(ident)) => Some(ident),
+        _ => None,
+    }
+}
+
+/// Split the items of a list around the ellipsis, which follows the item before it
+fn split_ellipsis(items: &[Spanned<Datum>]) -> Option<(usize, usize)> {
+    items
+        .iter()
+        .position(|(datum, _)| ident(datum) == Some(ELLIPSIS))
+        .filter(|position| *position > 0)
+        .map(|position| (position - 1, items.len() - position - 1))
+}
+
+/// What a pattern variable matched, a variable followed by `n` ellipses matches `n` levels of
+/// sequences
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Binding {
+    One(Spanned<Datum>),
+    Many(Vec<Binding>),
+}
+
+/// Pattern variables and what they matched
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Bindings {
+    bindings: HashMap<String, Binding>,
+}
+
+impl Bindings {
+    /// The datum matched by a variable which is not followed by an ellipsis
+    pub fn get(&self, name: &str) -> Option<&Spanned<Datum>> {
+        match self.bindings.get(name)? {
+            Binding::One(datum) => Some(datum),
+            Binding::Many(_) => None,
+        }
+    }
+    /// The datums matched by a variable which is followed by an ellipsis
+    pub fn get_all(&self, name: &str) -> Option<Vec<&Spanned<Datum>>> {
+        match self.bindings.get(name)? {
+            Binding::Many(bindings) => bindings
+                .iter()
+                .map(|binding| match binding {
+                    Binding::One(datum) => Some(datum),
+                    Binding::Many(_) => None,
+                })
+                .collect(),
+            Binding::One(_) => None,
+        }
+    }
+    pub fn binding(&self, name: &str) -> Option<&Binding> {
+        self.bindings.get(name)
+    }
+    /// Bind `name` for a template, such as to a symbol from `Context::gensym`
+    pub fn insert(&mut self, name: &str, datum: Spanned<Datum>) {
+        self.bindings.insert(name.to_owned(), Binding::One(datum));
+    }
+}
+
+/// A shape of datums written in Scheme syntax, as the patterns of `syntax-rules`.
+///
+/// An identifier in a pattern is a variable matching any datum, except `_`, which matches without
+/// binding, the literals and the head of the pattern, which match the same identifier. An item
+/// followed by `...` matches any number of datums.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    pattern: Spanned<Datum>,
+    literals: Vec<String>,
+}
+
+impl Pattern {
+    /// Panics if `source` is not exactly one datum, a variable which appears more than once is an
+    /// error
+    pub fn new(source: &str) -> Result<Self, Box<Simple<Token>>> {
+        Self::with_literals(source, &[])
+    }
+    /// A pattern whose `literals` match themselves rather than bind
+    pub fn with_literals(source: &str, literals: &[&str]) -> Result<Self, Box<Simple<Token>>> {
+        let pattern = read(source);
+        let head = match &pattern.0 {
+            Datum::List(items) => items.first().and_then(|(head, _)| ident(head)),
+            _ => None,
+        };
+        let literals = head
+            .into_iter()
+            .chain(literals.iter().copied())
+            .map(str::to_owned)
+            .collect();
+        let pattern = Self { pattern, literals };
+        let mut variables = Vec::new();
+        for (name, span) in pattern.variables(&pattern.pattern) {
+            if variables.contains(&name) {
+                return Err(Box::new(Simple::custom(
+                    span,
+                    format!("Pattern variable {} appears more than once", name),
+                )));
+            }
+            variables.push(name);
+        }
+        Ok(pattern)
+    }
+    /// The identifier which `datum` should start with, if the pattern is a list starting with one
+    pub fn head(&self) -> Option<&str> {
+        match &self.pattern.0 {
+            Datum::List(items) => items.first().and_then(|(head, _)| ident(head)),
+            _ => None,
+        }
+    }
+    /// Match `datum` against the pattern, returns what the variables matched
+    pub fn matches(&self, datum: &Spanned<Datum>) -> Option<Bindings> {
+        let mut bindings = Bindings::default();
+        self.match_datum(&self.pattern.0, datum, &mut bindings)
+            .then_some(bindings)
+    }
+    fn match_datum(
+        &self,
+        pattern: &Datum,
+        datum: &Spanned<Datum>,
+        bindings: &mut Bindings,
+    ) -> bool {
+        match (pattern, &datum.0) {
+            (Datum::Primitive(Primitive::Ident(name)), _) if name == "_" => true,
+            (Datum::Primitive(Primitive::Ident(name)), datum) if self.literals.contains(name) => {
+                ident(datum) == Some(name)
+            }
+            (Datum::Primitive(Primitive::Ident(name)), _) => {
+                bindings.insert(name, datum.clone());
+                true
+            }
+            (Datum::List(patterns), Datum::List(items)) => match split_ellipsis(patterns) {
+                None => {
+                    patterns.len() == items.len()
+                        && patterns
+                            .iter()
+                            .zip(items)
+                            .all(|((pattern, _), item)| self.match_datum(pattern, item, bindings))
+                }
+                Some((before, after)) => {
+                    if items.len() < before + after {
+                        return false;
+                    }
+                    let repeated = &patterns[before].0;
+                    let (head, rest) = items.split_at(before);
+                    let (middle, tail) = rest.split_at(rest.len() - after);
+                    let mut sequence = Vec::new();
+                    for item in middle {
+                        let mut nested = Bindings::default();
+                        if !self.match_datum(repeated, item, &mut nested) {
+                            return false;
+                        }
+                        sequence.push(nested);
+                    }
+                    for (name, _) in self.variables(&patterns[before]) {
+                        let matched = sequence
+                            .iter_mut()
+                            .map(|nested| nested.bindings.remove(&name))
+                            .collect::<Option<_>>();
+                        let Some(matched) = matched else {
+                            return false;
+                        };
+                        bindings.bindings.insert(name, Binding::Many(matched));
+                    }
+                    patterns[..before]
+                        .iter()
+                        .zip(head)
+                        .chain(patterns[before + 2..].iter().zip(tail))
+                        .all(|((pattern, _), item)| self.match_datum(pattern, item, bindings))
+                }
+            },
+            (pattern, datum) => pattern == datum,
+        }
+    }
+    /// Variables bound by `pattern`, in the order they are written
+    fn variables(&self, (pattern, span): &Spanned<Datum>) -> Vec<Spanned<String>> {
+        match pattern {
+            Datum::Primitive(Primitive::Ident(name))
+                if name != "_" && name != ELLIPSIS && !self.literals.contains(name) =>
+            {
+                vec![(name.clone(), span.clone())]
+            }
+            Datum::List(patterns) => patterns
+                .iter()
+                .flat_map(|pattern| self.variables(pattern))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Datums to build from `Bindings`, written in Scheme syntax.
+///
+/// A variable is replaced with the datum it matched, which keeps its span. An item followed by
+/// `...` is repeated for every datum its variables matched. Other datums are made up, they inherit
+/// the span of the call site.
+#[derive(Debug, Clone)]
+pub struct Template {
+    template: Spanned<Datum>,
+}
+
+impl Template {
+    /// Panics if `source` is not exactly one datum
+    pub fn new(source: &str) -> Self {
+        Self {
+            template: read(source),
+        }
+    }
+    /// Build the datums, `span` is the span of the call site
+    pub fn expand(
+        &self,
+        bindings: &Bindings,
+        span: &Span,
+    ) -> Result<Spanned<Datum>, Box<Simple<Token>>> {
+        let bindings = bindings
+            .bindings
+            .iter()
+            .map(|(name, binding)| (name.as_str(), binding))
+            .collect();
+        expand_template(&self.template.0, &bindings, span)
+    }
+}
+
+fn expand_template(
+    template: &Datum,
+    bindings: &HashMap<&str, &Binding>,
+    span: &Span,
+) -> Result<Spanned<Datum>, Box<Simple<Token>>> {
+    match template {
+        Datum::Primitive(Primitive::Ident(name)) => match bindings.get(name.as_str()) {
+            Some(Binding::One(datum)) => Ok(datum.clone()),
+            Some(Binding::Many(_)) => Err(Box::new(Simple::custom(
+                span.clone(),
+                format!(
+                    "Pattern variable {} should be followed by an ellipsis",
+                    name
+                ),
+            ))),
+            None => Ok((template.clone(), span.clone())),
+        },
+        Datum::List(templates) => {
+            let mut list = Vec::new();
+            let mut templates = templates.iter().peekable();
+            while let Some((template, _)) = templates.next() {
+                if templates
+                    .next_if(|(next, _)| ident(next) == Some(ELLIPSIS))
+                    .is_none()
+                {
+                    list.push(expand_template(template, bindings, span)?);
+                    continue;
+                }
+                let sequences: Vec<_> = template_variables(template)
+                    .into_iter()
+                    .filter_map(|name| match bindings.get(name) {
+                        Some(Binding::Many(sequence)) => Some((name, sequence)),
+                        _ => None,
+                    })
+                    .collect();
+                let Some((_, first)) = sequences.first() else {
+                    return Err(Box::new(Simple::custom(
+                        span.clone(),
+                        "No pattern variable followed by an ellipsis to repeat",
+                    )));
+                };
+                if sequences
+                    .iter()
+                    .any(|(_, sequence)| sequence.len() != first.len())
+                {
+                    return Err(Box::new(Simple::custom(
+                        span.clone(),
+                        "Pattern variables under the same ellipsis matched different lengths",
+                    )));
+                }
+                for index in 0..first.len() {
+                    let mut nested = bindings.clone();
+                    for (name, sequence) in &sequences {
+                        nested.insert(name, &sequence[index]);
+                    }
+                    list.push(expand_template(template, &nested, span)?);
+                }
+            }
+            Ok((Datum::List(list), span.clone()))
+        }
+        template => Ok((template.clone(), span.clone())),
+    }
+}
+
+fn template_variables(template: &Datum) -> Vec<&str> {
+    match template {
+        Datum::Primitive(Primitive::Ident(name)) => vec![name.as_str()],
+        Datum::List(templates) => templates
+            .iter()
+            .flat_map(|(template, _)| template_variables(template))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// A transformer of rules, as `syntax-rules`, which rewrites a datum with the template of the first
+/// pattern it matches
+///
+/// The templates are not hygienic, a template could bind a symbol from `Context::gensym` to avoid
+/// capturing the identifiers of the call site.
+#[derive(Debug, Clone, Default)]
+pub struct SyntaxRules {
+    rules: Vec<(Pattern, Template)>,
+}
+
+impl SyntaxRules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_rule(mut self, pattern: Pattern, template: Template) -> Self {
+        self.rules.push((pattern, template));
+        self
+    }
+}
+
+impl Transformer for SyntaxRules {
+    fn transform(
+        &self,
+        datum: &mut Spanned<Datum>,
+        _: &mut Context,
+    ) -> Result<bool, Box<Simple<Token>>> {
+        for (pattern, template) in &self.rules {
+            if let Some(bindings) = pattern.matches(datum) {
+                *datum = template.expand(&bindings, &datum.1)?;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
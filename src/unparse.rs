@@ -0,0 +1,325 @@
+use crate::*;
+
+/// Turn AST nodes back into the datums they are parsed from, so that parsing the written datums
+/// gives the same tree except spans.
+///
+/// `(define (f x) ...)` is unparsed as `(define f (lambda (x) ...))`, which parses into the same
+/// tree. `Expression::Error` has no source, it is unparsed as `Datum::Error`.
+pub trait Unparse {
+    /// The datum of `self`, made-up datums such as `lambda` take `span`
+    fn to_datum(&self, span: &Span) -> Datum;
+    /// Scheme source which parses into `self`
+    fn unparse(&self) -> String {
+        self.to_datum(&(0..0)).to_string()
+    }
+}
+
+impl<T: Unparse> Unparse for Spanned<T> {
+    fn to_datum(&self, _: &Span) -> Datum {
+        self.0.to_datum(&self.1)
+    }
+}
+
+fn spanned<T: Unparse>(node: &Spanned<T>) -> Spanned<Datum> {
+    (node.0.to_datum(&node.1), node.1.clone())
+}
+
+fn symbol(name: &str, span: &Span) -> Spanned<Datum> {
+    (Primitive::Ident(name.to_owned()).into(), span.clone())
+}
+
+fn identifier((name, span): &Spanned<String>) -> Spanned<Datum> {
+    symbol(name, span)
+}
+
+/// `(head items ...)`
+fn form(head: Spanned<Datum>, items: impl IntoIterator<Item = Spanned<Datum>>) -> Datum {
+    Datum::List(Some(head).into_iter().chain(items).collect())
+}
+
+/// `(args ... . rest)`, or `rest` if there is no argument
+fn formals(
+    args: &[Spanned<String>],
+    rest: &Option<Spanned<String>>,
+    span: &Span,
+) -> Spanned<Datum> {
+    match (args, rest) {
+        ([], Some(rest)) => identifier(rest),
+        _ => {
+            let mut list: Vec<_> = args.iter().map(identifier).collect();
+            if let Some(rest) = rest {
+                list.push((Datum::Keyword("."), rest.1.clone()));
+                list.push(identifier(rest));
+            }
+            (Datum::List(list), span.clone())
+        }
+    }
+}
+
+impl ProcedureBody {
+    /// Datums of the definitions and expressions
+    pub fn to_datums(&self) -> Vec<Spanned<Datum>> {
+        self.defs
+            .iter()
+            .map(spanned)
+            .chain(self.exprs.iter().map(spanned))
+            .chain(Some(spanned(&self.last_expr)))
+            .collect()
+    }
+}
+
+impl Program {
+    /// Datums of the libraries, the imports and the body
+    pub fn to_datums(&self) -> Vec<Spanned<Datum>> {
+        let mut datums: Vec<_> = self.libraries.iter().map(spanned).collect();
+        datums.extend(self.imports.iter().map(|(sets, span)| {
+            (
+                form(symbol("import", span), sets.iter().map(spanned)),
+                span.clone(),
+            )
+        }));
+        datums.extend(self.body.iter().flat_map(ProcedureBody::to_datums));
+        datums
+    }
+    /// Scheme source which parses into `self`, a line for every top level datum
+    pub fn unparse(&self) -> String {
+        self.to_datums()
+            .iter()
+            .map(|(datum, _)| format!("{}\n", datum))
+            .collect()
+    }
+}
+
+impl Unparse for Library {
+    fn to_datum(&self, span: &Span) -> Datum {
+        form(
+            symbol("define-library", span),
+            Some(spanned(&self.name))
+                .into_iter()
+                .chain(self.declarations.iter().map(spanned)),
+        )
+    }
+}
+
+impl Unparse for LibraryName {
+    fn to_datum(&self, _: &Span) -> Datum {
+        Datum::List(
+            self.iter()
+                .map(|(part, span)| (Datum::Primitive(part.clone()), span.clone()))
+                .collect(),
+        )
+    }
+}
+
+impl Unparse for LibraryDeclaration {
+    fn to_datum(&self, span: &Span) -> Datum {
+        let strings = |files: &Vec<Spanned<String>>| {
+            files
+                .iter()
+                .map(|(file, span)| (Primitive::String(file.clone()).into(), span.clone()))
+                .collect::<Vec<_>>()
+        };
+        match self {
+            LibraryDeclaration::Export(specs) => {
+                form(symbol("export", span), specs.iter().map(spanned))
+            }
+            LibraryDeclaration::Import(sets) => {
+                form(symbol("import", span), sets.iter().map(spanned))
+            }
+            LibraryDeclaration::Begin { defs, exprs } => form(
+                symbol("begin", span),
+                defs.iter().map(spanned).chain(exprs.iter().map(spanned)),
+            ),
+            LibraryDeclaration::Include {
+                case_insensitive,
+                files,
+            } => form(
+                symbol(
+                    if *case_insensitive {
+                        "include-ci"
+                    } else {
+                        "include"
+                    },
+                    span,
+                ),
+                strings(files),
+            ),
+            LibraryDeclaration::IncludeLibraryDeclarations(files) => {
+                form(symbol("include-library-declarations", span), strings(files))
+            }
+        }
+    }
+}
+
+impl Unparse for ExportSpec {
+    fn to_datum(&self, span: &Span) -> Datum {
+        match self {
+            ExportSpec::Ident(ident) => Primitive::Ident(ident.clone()).into(),
+            ExportSpec::Rename(rename) => form(symbol("rename", span), rename.pair()),
+        }
+    }
+}
+
+impl Rename {
+    fn pair(&self) -> [Spanned<Datum>; 2] {
+        [identifier(&self.0), identifier(&self.1)]
+    }
+}
+
+impl Unparse for Rename {
+    fn to_datum(&self, _: &Span) -> Datum {
+        Datum::List(self.pair().into())
+    }
+}
+
+impl Unparse for ImportSet {
+    fn to_datum(&self, span: &Span) -> Datum {
+        match self {
+            ImportSet::Library(name) => name.to_datum(span),
+            ImportSet::Only(set, idents) => form(
+                symbol("only", span),
+                Some(spanned(set))
+                    .into_iter()
+                    .chain(idents.iter().map(identifier)),
+            ),
+            ImportSet::Except(set, idents) => form(
+                symbol("except", span),
+                Some(spanned(set))
+                    .into_iter()
+                    .chain(idents.iter().map(identifier)),
+            ),
+            ImportSet::Prefix(set, prefix) => {
+                form(symbol("prefix", span), [spanned(set), identifier(prefix)])
+            }
+            ImportSet::Rename(set, renames) => form(
+                symbol("rename", span),
+                Some(spanned(set))
+                    .into_iter()
+                    .chain(renames.iter().map(spanned)),
+            ),
+        }
+    }
+}
+
+impl Unparse for Definition {
+    fn to_datum(&self, span: &Span) -> Datum {
+        match self {
+            Definition::Variable(name, expr) => form(
+                (Datum::Keyword("define"), span.clone()),
+                [identifier(name), spanned(expr)],
+            ),
+            Definition::Values(formals, expr) => form(
+                symbol("define-values", span),
+                [spanned(formals), spanned(expr)],
+            ),
+            Definition::Begin(defs) => form(symbol("begin", span), defs.iter().map(spanned)),
+        }
+    }
+}
+
+impl Unparse for Formals {
+    fn to_datum(&self, span: &Span) -> Datum {
+        formals(&self.args, &self.rest, span).0
+    }
+}
+
+impl Unparse for ValuesBinding {
+    fn to_datum(&self, _: &Span) -> Datum {
+        Datum::List(vec![spanned(&self.0), spanned(&self.1)])
+    }
+}
+
+impl Unparse for Expression {
+    fn to_datum(&self, span: &Span) -> Datum {
+        match self {
+            Expression::ProcedureCall { operator, args } => {
+                form(spanned(operator), args.iter().map(spanned))
+            }
+            Expression::Primitive(primitive) => Datum::Primitive(primitive.clone()),
+            Expression::Quote(datum) => form(symbol("quote", span), [datum.clone()]),
+            Expression::Procedure { args, rest, body } => form(
+                (Datum::Keyword("lambda"), span.clone()),
+                Some(formals(args, rest, span))
+                    .into_iter()
+                    .chain(body.to_datums()),
+            ),
+            Expression::Conditional {
+                test,
+                conseq,
+                alter,
+            } => form(
+                (Datum::Keyword("if"), span.clone()),
+                [test, conseq]
+                    .into_iter()
+                    .chain(alter)
+                    .map(|expr| spanned(expr)),
+            ),
+            Expression::Assignment(name, expr) => form(
+                (Datum::Keyword("set!"), span.clone()),
+                [identifier(name), spanned(expr)],
+            ),
+            Expression::LetValues {
+                sequential,
+                bindings,
+                body,
+            } => form(
+                symbol(
+                    if *sequential {
+                        "let*-values"
+                    } else {
+                        "let-values"
+                    },
+                    span,
+                ),
+                Some((
+                    Datum::List(bindings.iter().map(spanned).collect()),
+                    span.clone(),
+                ))
+                .into_iter()
+                .chain(body.to_datums()),
+            ),
+            Expression::Receive {
+                formals,
+                expr,
+                body,
+            } => form(
+                symbol("receive", span),
+                [spanned(formals), spanned(expr)]
+                    .into_iter()
+                    .chain(body.to_datums()),
+            ),
+            Expression::Begin { defs, exprs } => form(
+                symbol("begin", span),
+                defs.iter().map(spanned).chain(exprs.iter().map(spanned)),
+            ),
+            Expression::Guard { var, clauses, body } => form(
+                symbol("guard", span),
+                Some((
+                    form(identifier(var), clauses.iter().map(spanned)),
+                    span.clone(),
+                ))
+                .into_iter()
+                .chain(body.to_datums()),
+            ),
+            Expression::Error => Datum::Error,
+        }
+    }
+}
+
+impl Unparse for CondClause {
+    fn to_datum(&self, span: &Span) -> Datum {
+        match self {
+            CondClause::Test { test, body } => form(spanned(test), body.iter().map(spanned)),
+            CondClause::Arrow { test, receiver } => {
+                Datum::List(vec![spanned(test), symbol("=>", span), spanned(receiver)])
+            }
+            CondClause::Else(body) => form(symbol("else", span), body.iter().map(spanned)),
+        }
+    }
+}
+
+impl Unparse for Datum {
+    fn to_datum(&self, _: &Span) -> Datum {
+        self.clone()
+    }
+}
@@ -0,0 +1,777 @@
+//! Traversals of the AST.
+//!
+//! [`Visitor`] borrows the nodes, [`VisitorMut`] mutates them in place and [`Fold`] rebuilds them
+//! by value. Every method defaults to the walker of the same node, `walk_*`, `walk_*_mut` or
+//! `fold_*`, which visits the children of the node in source order. An overridden method calls the
+//! walker to keep descending.
+//!
+//! The walkers destructure every node without `..` or wildcard arms, so a new variant or field
+//! fails to compile until it is walked.
+
+use crate::*;
+
+/// Visits the AST by reference, the nodes could be kept as long as the AST
+pub trait Visitor<'ast> {
+    fn visit_program(&mut self, program: &'ast Program) {
+        walk_program(self, program)
+    }
+    fn visit_library(&mut self, library: &'ast Spanned<Library>) {
+        walk_library(self, library)
+    }
+    fn visit_library_declaration(&mut self, declaration: &'ast Spanned<LibraryDeclaration>) {
+        walk_library_declaration(self, declaration)
+    }
+    fn visit_import_set(&mut self, set: &'ast Spanned<ImportSet>) {
+        walk_import_set(self, set)
+    }
+    fn visit_body(&mut self, body: &'ast ProcedureBody) {
+        walk_body(self, body)
+    }
+    fn visit_definition(&mut self, def: &'ast Spanned<Definition>) {
+        walk_definition(self, def)
+    }
+    fn visit_formals(&mut self, formals: &'ast Spanned<Formals>) {
+        walk_formals(self, formals)
+    }
+    fn visit_values_binding(&mut self, binding: &'ast Spanned<ValuesBinding>) {
+        walk_values_binding(self, binding)
+    }
+    fn visit_expression(&mut self, expr: &'ast Spanned<Expression>) {
+        walk_expression(self, expr)
+    }
+    fn visit_cond_clause(&mut self, clause: &'ast Spanned<CondClause>) {
+        walk_cond_clause(self, clause)
+    }
+    /// A name bound by a definition, a formal parameter or a `guard` variable
+    fn visit_binding(&mut self, _name: &'ast Spanned<String>) {}
+    /// A variable reference, or the variable assigned by `set!`
+    fn visit_variable(&mut self, _name: &'ast str, _span: &'ast Span) {}
+}
+
+pub fn walk_program<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, program: &'ast Program) {
+    let Program {
+        libraries,
+        imports,
+        body,
+    } = program;
+    for library in libraries {
+        visitor.visit_library(library);
+    }
+    for set in imports.iter().flat_map(|(sets, _)| sets) {
+        visitor.visit_import_set(set);
+    }
+    if let Some(body) = body {
+        visitor.visit_body(body);
+    }
+}
+
+pub fn walk_library<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    (library, _): &'ast Spanned<Library>,
+) {
+    let Library {
+        name: _,
+        declarations,
+    } = library;
+    for declaration in declarations {
+        visitor.visit_library_declaration(declaration);
+    }
+}
+
+pub fn walk_library_declaration<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    (declaration, _): &'ast Spanned<LibraryDeclaration>,
+) {
+    match declaration {
+        LibraryDeclaration::Import(sets) => {
+            for set in sets {
+                visitor.visit_import_set(set);
+            }
+        }
+        LibraryDeclaration::Begin { defs, exprs } => {
+            for def in defs {
+                visitor.visit_definition(def);
+            }
+            for expr in exprs {
+                visitor.visit_expression(expr);
+            }
+        }
+        LibraryDeclaration::Export(_)
+        | LibraryDeclaration::Include {
+            case_insensitive: _,
+            files: _,
+        }
+        | LibraryDeclaration::IncludeLibraryDeclarations(_) => {}
+    }
+}
+
+pub fn walk_import_set<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    (set, _): &'ast Spanned<ImportSet>,
+) {
+    match set {
+        ImportSet::Library(_) => {}
+        ImportSet::Only(set, _)
+        | ImportSet::Except(set, _)
+        | ImportSet::Prefix(set, _)
+        | ImportSet::Rename(set, _) => visitor.visit_import_set(set),
+    }
+}
+
+pub fn walk_body<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, body: &'ast ProcedureBody) {
+    let ProcedureBody {
+        defs,
+        exprs,
+        last_expr,
+    } = body;
+    for def in defs {
+        visitor.visit_definition(def);
+    }
+    for expr in exprs {
+        visitor.visit_expression(expr);
+    }
+    visitor.visit_expression(last_expr);
+}
+
+pub fn walk_definition<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    (def, _): &'ast Spanned<Definition>,
+) {
+    match def {
+        Definition::Variable(name, expr) => {
+            visitor.visit_binding(name);
+            visitor.visit_expression(expr);
+        }
+        Definition::Values(formals, expr) => {
+            visitor.visit_formals(formals);
+            visitor.visit_expression(expr);
+        }
+        Definition::Begin(defs) => {
+            for def in defs {
+                visitor.visit_definition(def);
+            }
+        }
+    }
+}
+
+pub fn walk_formals<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    (formals, _): &'ast Spanned<Formals>,
+) {
+    let Formals { args, rest } = formals;
+    for arg in args.iter().chain(rest) {
+        visitor.visit_binding(arg);
+    }
+}
+
+pub fn walk_values_binding<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    (ValuesBinding(formals, expr), _): &'ast Spanned<ValuesBinding>,
+) {
+    visitor.visit_formals(formals);
+    visitor.visit_expression(expr);
+}
+
+pub fn walk_expression<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    (expr, span): &'ast Spanned<Expression>,
+) {
+    match expr {
+        Expression::ProcedureCall { operator, args } => {
+            visitor.visit_expression(operator);
+            for arg in args {
+                visitor.visit_expression(arg);
+            }
+        }
+        Expression::Primitive(Primitive::Ident(name)) => visitor.visit_variable(name, span),
+        // Quoted data is not code, so there is nothing to visit in it
+        Expression::Primitive(_) | Expression::Quote(_) | Expression::Error => {}
+        Expression::Procedure { args, rest, body } => {
+            for arg in args.iter().chain(rest) {
+                visitor.visit_binding(arg);
+            }
+            visitor.visit_body(body);
+        }
+        Expression::Conditional {
+            test,
+            conseq,
+            alter,
+        } => {
+            visitor.visit_expression(test);
+            visitor.visit_expression(conseq);
+            if let Some(alter) = alter {
+                visitor.visit_expression(alter);
+            }
+        }
+        Expression::Assignment((name, span), expr) => {
+            visitor.visit_variable(name, span);
+            visitor.visit_expression(expr);
+        }
+        Expression::LetValues {
+            sequential: _,
+            bindings,
+            body,
+        } => {
+            for binding in bindings {
+                visitor.visit_values_binding(binding);
+            }
+            visitor.visit_body(body);
+        }
+        Expression::Receive {
+            formals,
+            expr,
+            body,
+        } => {
+            visitor.visit_formals(formals);
+            visitor.visit_expression(expr);
+            visitor.visit_body(body);
+        }
+        Expression::Begin { defs, exprs } => {
+            for def in defs {
+                visitor.visit_definition(def);
+            }
+            for expr in exprs {
+                visitor.visit_expression(expr);
+            }
+        }
+        Expression::Guard { var, clauses, body } => {
+            visitor.visit_binding(var);
+            for clause in clauses {
+                visitor.visit_cond_clause(clause);
+            }
+            visitor.visit_body(body);
+        }
+    }
+}
+
+pub fn walk_cond_clause<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    (clause, _): &'ast Spanned<CondClause>,
+) {
+    match clause {
+        CondClause::Test { test, body } => {
+            visitor.visit_expression(test);
+            for expr in body {
+                visitor.visit_expression(expr);
+            }
+        }
+        CondClause::Arrow { test, receiver } => {
+            visitor.visit_expression(test);
+            visitor.visit_expression(receiver);
+        }
+        CondClause::Else(body) => {
+            for expr in body {
+                visitor.visit_expression(expr);
+            }
+        }
+    }
+}
+
+/// Visits the AST by mutable reference, to rewrite it in place
+pub trait VisitorMut {
+    fn visit_program(&mut self, program: &mut Program) {
+        walk_program_mut(self, program)
+    }
+    fn visit_library(&mut self, library: &mut Spanned<Library>) {
+        walk_library_mut(self, library)
+    }
+    fn visit_library_declaration(&mut self, declaration: &mut Spanned<LibraryDeclaration>) {
+        walk_library_declaration_mut(self, declaration)
+    }
+    fn visit_import_set(&mut self, set: &mut Spanned<ImportSet>) {
+        walk_import_set_mut(self, set)
+    }
+    fn visit_body(&mut self, body: &mut ProcedureBody) {
+        walk_body_mut(self, body)
+    }
+    fn visit_definition(&mut self, def: &mut Spanned<Definition>) {
+        walk_definition_mut(self, def)
+    }
+    fn visit_formals(&mut self, formals: &mut Spanned<Formals>) {
+        walk_formals_mut(self, formals)
+    }
+    fn visit_values_binding(&mut self, binding: &mut Spanned<ValuesBinding>) {
+        walk_values_binding_mut(self, binding)
+    }
+    fn visit_expression(&mut self, expr: &mut Spanned<Expression>) {
+        walk_expression_mut(self, expr)
+    }
+    fn visit_cond_clause(&mut self, clause: &mut Spanned<CondClause>) {
+        walk_cond_clause_mut(self, clause)
+    }
+    /// A name bound by a definition, a formal parameter or a `guard` variable
+    fn visit_binding(&mut self, _name: &mut Spanned<String>) {}
+    /// A variable reference, or the variable assigned by `set!`
+    fn visit_variable(&mut self, _name: &mut String, _span: &mut Span) {}
+}
+
+pub fn walk_program_mut<V: VisitorMut + ?Sized>(visitor: &mut V, program: &mut Program) {
+    let Program {
+        libraries,
+        imports,
+        body,
+    } = program;
+    for library in libraries {
+        visitor.visit_library(library);
+    }
+    for set in imports.iter_mut().flat_map(|(sets, _)| sets) {
+        visitor.visit_import_set(set);
+    }
+    if let Some(body) = body {
+        visitor.visit_body(body);
+    }
+}
+
+pub fn walk_library_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    (library, _): &mut Spanned<Library>,
+) {
+    let Library {
+        name: _,
+        declarations,
+    } = library;
+    for declaration in declarations {
+        visitor.visit_library_declaration(declaration);
+    }
+}
+
+pub fn walk_library_declaration_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    (declaration, _): &mut Spanned<LibraryDeclaration>,
+) {
+    match declaration {
+        LibraryDeclaration::Import(sets) => {
+            for set in sets {
+                visitor.visit_import_set(set);
+            }
+        }
+        LibraryDeclaration::Begin { defs, exprs } => {
+            for def in defs {
+                visitor.visit_definition(def);
+            }
+            for expr in exprs {
+                visitor.visit_expression(expr);
+            }
+        }
+        LibraryDeclaration::Export(_)
+        | LibraryDeclaration::Include {
+            case_insensitive: _,
+            files: _,
+        }
+        | LibraryDeclaration::IncludeLibraryDeclarations(_) => {}
+    }
+}
+
+pub fn walk_import_set_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    (set, _): &mut Spanned<ImportSet>,
+) {
+    match set {
+        ImportSet::Library(_) => {}
+        ImportSet::Only(set, _)
+        | ImportSet::Except(set, _)
+        | ImportSet::Prefix(set, _)
+        | ImportSet::Rename(set, _) => visitor.visit_import_set(set),
+    }
+}
+
+pub fn walk_body_mut<V: VisitorMut + ?Sized>(visitor: &mut V, body: &mut ProcedureBody) {
+    let ProcedureBody {
+        defs,
+        exprs,
+        last_expr,
+    } = body;
+    for def in defs {
+        visitor.visit_definition(def);
+    }
+    for expr in exprs {
+        visitor.visit_expression(expr);
+    }
+    visitor.visit_expression(last_expr);
+}
+
+pub fn walk_definition_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    (def, _): &mut Spanned<Definition>,
+) {
+    match def {
+        Definition::Variable(name, expr) => {
+            visitor.visit_binding(name);
+            visitor.visit_expression(expr);
+        }
+        Definition::Values(formals, expr) => {
+            visitor.visit_formals(formals);
+            visitor.visit_expression(expr);
+        }
+        Definition::Begin(defs) => {
+            for def in defs {
+                visitor.visit_definition(def);
+            }
+        }
+    }
+}
+
+pub fn walk_formals_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    (formals, _): &mut Spanned<Formals>,
+) {
+    let Formals { args, rest } = formals;
+    for arg in args.iter_mut().chain(rest) {
+        visitor.visit_binding(arg);
+    }
+}
+
+pub fn walk_values_binding_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    (ValuesBinding(formals, expr), _): &mut Spanned<ValuesBinding>,
+) {
+    visitor.visit_formals(formals);
+    visitor.visit_expression(expr);
+}
+
+pub fn walk_expression_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    (expr, span): &mut Spanned<Expression>,
+) {
+    match expr {
+        Expression::ProcedureCall { operator, args } => {
+            visitor.visit_expression(operator);
+            for arg in args {
+                visitor.visit_expression(arg);
+            }
+        }
+        Expression::Primitive(Primitive::Ident(name)) => visitor.visit_variable(name, span),
+        // Quoted data is not code, so there is nothing to visit in it
+        Expression::Primitive(_) | Expression::Quote(_) | Expression::Error => {}
+        Expression::Procedure { args, rest, body } => {
+            for arg in args.iter_mut().chain(rest) {
+                visitor.visit_binding(arg);
+            }
+            visitor.visit_body(body);
+        }
+        Expression::Conditional {
+            test,
+            conseq,
+            alter,
+        } => {
+            visitor.visit_expression(test);
+            visitor.visit_expression(conseq);
+            if let Some(alter) = alter {
+                visitor.visit_expression(alter);
+            }
+        }
+        Expression::Assignment((name, span), expr) => {
+            visitor.visit_variable(name, span);
+            visitor.visit_expression(expr);
+        }
+        Expression::LetValues {
+            sequential: _,
+            bindings,
+            body,
+        } => {
+            for binding in bindings {
+                visitor.visit_values_binding(binding);
+            }
+            visitor.visit_body(body);
+        }
+        Expression::Receive {
+            formals,
+            expr,
+            body,
+        } => {
+            visitor.visit_formals(formals);
+            visitor.visit_expression(expr);
+            visitor.visit_body(body);
+        }
+        Expression::Begin { defs, exprs } => {
+            for def in defs {
+                visitor.visit_definition(def);
+            }
+            for expr in exprs {
+                visitor.visit_expression(expr);
+            }
+        }
+        Expression::Guard { var, clauses, body } => {
+            visitor.visit_binding(var);
+            for clause in clauses {
+                visitor.visit_cond_clause(clause);
+            }
+            visitor.visit_body(body);
+        }
+    }
+}
+
+pub fn walk_cond_clause_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    (clause, _): &mut Spanned<CondClause>,
+) {
+    match clause {
+        CondClause::Test { test, body } => {
+            visitor.visit_expression(test);
+            for expr in body {
+                visitor.visit_expression(expr);
+            }
+        }
+        CondClause::Arrow { test, receiver } => {
+            visitor.visit_expression(test);
+            visitor.visit_expression(receiver);
+        }
+        CondClause::Else(body) => {
+            for expr in body {
+                visitor.visit_expression(expr);
+            }
+        }
+    }
+}
+
+/// Rebuilds the AST by value, a node could be replaced by another one
+pub trait Fold {
+    fn fold_program(&mut self, program: Program) -> Program {
+        fold_program(self, program)
+    }
+    fn fold_library(&mut self, library: Spanned<Library>) -> Spanned<Library> {
+        fold_library(self, library)
+    }
+    fn fold_library_declaration(
+        &mut self,
+        declaration: Spanned<LibraryDeclaration>,
+    ) -> Spanned<LibraryDeclaration> {
+        fold_library_declaration(self, declaration)
+    }
+    fn fold_import_set(&mut self, set: Spanned<ImportSet>) -> Spanned<ImportSet> {
+        fold_import_set(self, set)
+    }
+    fn fold_body(&mut self, body: ProcedureBody) -> ProcedureBody {
+        fold_body(self, body)
+    }
+    fn fold_definition(&mut self, def: Spanned<Definition>) -> Spanned<Definition> {
+        fold_definition(self, def)
+    }
+    fn fold_formals(&mut self, formals: Spanned<Formals>) -> Spanned<Formals> {
+        fold_formals(self, formals)
+    }
+    fn fold_values_binding(&mut self, binding: Spanned<ValuesBinding>) -> Spanned<ValuesBinding> {
+        fold_values_binding(self, binding)
+    }
+    fn fold_expression(&mut self, expr: Spanned<Expression>) -> Spanned<Expression> {
+        fold_expression(self, expr)
+    }
+    fn fold_cond_clause(&mut self, clause: Spanned<CondClause>) -> Spanned<CondClause> {
+        fold_cond_clause(self, clause)
+    }
+    /// A name bound by a definition, a formal parameter or a `guard` variable
+    fn fold_binding(&mut self, name: Spanned<String>) -> Spanned<String> {
+        name
+    }
+    /// A variable reference, or the variable assigned by `set!`
+    fn fold_variable(&mut self, name: Spanned<String>) -> Spanned<String> {
+        name
+    }
+}
+
+fn fold_all<T, F: Fold + ?Sized>(
+    folder: &mut F,
+    nodes: Vec<T>,
+    fold: fn(&mut F, T) -> T,
+) -> Vec<T> {
+    nodes.into_iter().map(|node| fold(folder, node)).collect()
+}
+
+pub fn fold_program<F: Fold + ?Sized>(folder: &mut F, program: Program) -> Program {
+    let Program {
+        libraries,
+        imports,
+        body,
+    } = program;
+    Program {
+        libraries: fold_all(folder, libraries, F::fold_library),
+        imports: imports
+            .into_iter()
+            .map(|(sets, span)| (fold_all(folder, sets, F::fold_import_set), span))
+            .collect(),
+        body: body.map(|body| folder.fold_body(body)),
+    }
+}
+
+pub fn fold_library<F: Fold + ?Sized>(
+    folder: &mut F,
+    (library, span): Spanned<Library>,
+) -> Spanned<Library> {
+    let Library { name, declarations } = library;
+    let declarations = fold_all(folder, declarations, F::fold_library_declaration);
+    (Library { name, declarations }, span)
+}
+
+pub fn fold_library_declaration<F: Fold + ?Sized>(
+    folder: &mut F,
+    (declaration, span): Spanned<LibraryDeclaration>,
+) -> Spanned<LibraryDeclaration> {
+    let declaration = match declaration {
+        LibraryDeclaration::Import(sets) => {
+            LibraryDeclaration::Import(fold_all(folder, sets, F::fold_import_set))
+        }
+        LibraryDeclaration::Begin { defs, exprs } => LibraryDeclaration::Begin {
+            defs: fold_all(folder, defs, F::fold_definition),
+            exprs: fold_all(folder, exprs, F::fold_expression),
+        },
+        declaration @ (LibraryDeclaration::Export(_)
+        | LibraryDeclaration::Include {
+            case_insensitive: _,
+            files: _,
+        }
+        | LibraryDeclaration::IncludeLibraryDeclarations(_)) => declaration,
+    };
+    (declaration, span)
+}
+
+pub fn fold_import_set<F: Fold + ?Sized>(
+    folder: &mut F,
+    (set, span): Spanned<ImportSet>,
+) -> Spanned<ImportSet> {
+    let set = match set {
+        ImportSet::Library(name) => ImportSet::Library(name),
+        ImportSet::Only(set, idents) => {
+            ImportSet::Only(Box::new(folder.fold_import_set(*set)), idents)
+        }
+        ImportSet::Except(set, idents) => {
+            ImportSet::Except(Box::new(folder.fold_import_set(*set)), idents)
+        }
+        ImportSet::Prefix(set, prefix) => {
+            ImportSet::Prefix(Box::new(folder.fold_import_set(*set)), prefix)
+        }
+        ImportSet::Rename(set, renames) => {
+            ImportSet::Rename(Box::new(folder.fold_import_set(*set)), renames)
+        }
+    };
+    (set, span)
+}
+
+pub fn fold_body<F: Fold + ?Sized>(folder: &mut F, body: ProcedureBody) -> ProcedureBody {
+    let ProcedureBody {
+        defs,
+        exprs,
+        last_expr,
+    } = body;
+    ProcedureBody {
+        defs: fold_all(folder, defs, F::fold_definition),
+        exprs: fold_all(folder, exprs, F::fold_expression),
+        last_expr: folder.fold_expression(last_expr),
+    }
+}
+
+pub fn fold_definition<F: Fold + ?Sized>(
+    folder: &mut F,
+    (def, span): Spanned<Definition>,
+) -> Spanned<Definition> {
+    let def = match def {
+        Definition::Variable(name, expr) => {
+            Definition::Variable(folder.fold_binding(name), folder.fold_expression(expr))
+        }
+        Definition::Values(formals, expr) => {
+            Definition::Values(folder.fold_formals(formals), folder.fold_expression(expr))
+        }
+        Definition::Begin(defs) => Definition::Begin(fold_all(folder, defs, F::fold_definition)),
+    };
+    (def, span)
+}
+
+pub fn fold_formals<F: Fold + ?Sized>(
+    folder: &mut F,
+    (formals, span): Spanned<Formals>,
+) -> Spanned<Formals> {
+    let Formals { args, rest } = formals;
+    let formals = Formals {
+        args: fold_all(folder, args, F::fold_binding),
+        rest: rest.map(|rest| folder.fold_binding(rest)),
+    };
+    (formals, span)
+}
+
+pub fn fold_values_binding<F: Fold + ?Sized>(
+    folder: &mut F,
+    (ValuesBinding(formals, expr), span): Spanned<ValuesBinding>,
+) -> Spanned<ValuesBinding> {
+    let binding = ValuesBinding(folder.fold_formals(formals), folder.fold_expression(expr));
+    (binding, span)
+}
+
+pub fn fold_expression<F: Fold + ?Sized>(
+    folder: &mut F,
+    (expr, span): Spanned<Expression>,
+) -> Spanned<Expression> {
+    let expr = match expr {
+        Expression::ProcedureCall { operator, args } => Expression::ProcedureCall {
+            operator: Box::new(folder.fold_expression(*operator)),
+            args: fold_all(folder, args, F::fold_expression),
+        },
+        Expression::Primitive(Primitive::Ident(name)) => {
+            let (name, span) = folder.fold_variable((name, span));
+            return (Primitive::Ident(name).into(), span);
+        }
+        expr @ (Expression::Primitive(_) | Expression::Quote(_) | Expression::Error) => expr,
+        Expression::Procedure { args, rest, body } => Expression::Procedure {
+            args: fold_all(folder, args, F::fold_binding),
+            rest: rest.map(|rest| folder.fold_binding(rest)),
+            body: Box::new(folder.fold_body(*body)),
+        },
+        Expression::Conditional {
+            test,
+            conseq,
+            alter,
+        } => Expression::Conditional {
+            test: Box::new(folder.fold_expression(*test)),
+            conseq: Box::new(folder.fold_expression(*conseq)),
+            alter: alter.map(|alter| Box::new(folder.fold_expression(*alter))),
+        },
+        Expression::Assignment(name, expr) => Expression::Assignment(
+            folder.fold_variable(name),
+            Box::new(folder.fold_expression(*expr)),
+        ),
+        Expression::LetValues {
+            sequential,
+            bindings,
+            body,
+        } => Expression::LetValues {
+            sequential,
+            bindings: fold_all(folder, bindings, F::fold_values_binding),
+            body: Box::new(folder.fold_body(*body)),
+        },
+        Expression::Receive {
+            formals,
+            expr,
+            body,
+        } => Expression::Receive {
+            formals: folder.fold_formals(formals),
+            expr: Box::new(folder.fold_expression(*expr)),
+            body: Box::new(folder.fold_body(*body)),
+        },
+        Expression::Begin { defs, exprs } => Expression::Begin {
+            defs: fold_all(folder, defs, F::fold_definition),
+            exprs: fold_all(folder, exprs, F::fold_expression),
+        },
+        Expression::Guard { var, clauses, body } => Expression::Guard {
+            var: folder.fold_binding(var),
+            clauses: fold_all(folder, clauses, F::fold_cond_clause),
+            body: Box::new(folder.fold_body(*body)),
+        },
+    };
+    (expr, span)
+}
+
+pub fn fold_cond_clause<F: Fold + ?Sized>(
+    folder: &mut F,
+    (clause, span): Spanned<CondClause>,
+) -> Spanned<CondClause> {
+    let clause = match clause {
+        CondClause::Test { test, body } => CondClause::Test {
+            test: folder.fold_expression(test),
+            body: fold_all(folder, body, F::fold_expression),
+        },
+        CondClause::Arrow { test, receiver } => CondClause::Arrow {
+            test: folder.fold_expression(test),
+            receiver: folder.fold_expression(receiver),
+        },
+        CondClause::Else(body) => CondClause::Else(fold_all(folder, body, F::fold_expression)),
+    };
+    (clause, span)
+}
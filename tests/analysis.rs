@@ -0,0 +1,284 @@
+use scheme_parser::analysis::closure::*;
+use scheme_parser::analysis::exception::*;
+use scheme_parser::analysis::scope::*;
+use scheme_parser::transformer::pattern::{Pattern, SyntaxRules, Template};
+use scheme_parser::*;
+
+fn parse(source: &str) -> Program {
+    Parser::default().parse(source, "test.scm").unwrap()
+}
+
+#[test]
+fn exception_handlers() {
+    let source = "(raise 1)
+(guard (e ((error-object? e) => error-object-message))
+  (guard (e2 (else (raise e2)))
+    (map error-object-irritants (list (error oops 1)))))";
+    let exceptions = Exceptions::analyze(&parse(source));
+    assert_eq!(exceptions.handlers.len(), 2);
+    assert_eq!(exceptions.handlers[0].var.0, "e");
+    assert_eq!(exceptions.handlers[1].parent, Some(0));
+
+    let raises = exceptions
+        .raises
+        .iter()
+        .map(|raise| (raise.kind, &source[raise.span.clone()], raise.handler))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        raises,
+        vec![
+            (RaiseKind::Raise, "(raise 1)", None),
+            (RaiseKind::Raise, "(raise e2)", Some(0)),
+            (RaiseKind::Error, "(error oops 1)", Some(1)),
+        ]
+    );
+    assert_eq!(exceptions.unguarded_raises().count(), 1);
+
+    let uses = exceptions
+        .error_object_uses
+        .iter()
+        .map(|u| (u.access, u.handler, u.clause_of))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        uses,
+        vec![
+            (ErrorObjectAccess::Predicate, None, Some(0)),
+            (ErrorObjectAccess::Message, None, Some(0)),
+            (ErrorObjectAccess::Irritants, Some(1), None),
+        ]
+    );
+
+    let offset = source.find("oops").unwrap();
+    assert_eq!(exceptions.handler_at(offset), Some(1));
+    assert!(!exceptions.is_guarded(&(0..9)));
+
+    let source = "(define (f raise) (define (error x) x) (raise (error 1)) (error-object? 1))
+(define (error-object? x) #t)
+(error 'f)";
+    let exceptions = Exceptions::analyze(&parse(source));
+    let raises = exceptions
+        .raises
+        .iter()
+        .map(|raise| &source[raise.span.clone()])
+        .collect::<Vec<_>>();
+    assert_eq!(raises, vec!["(error 'f)"]);
+    assert_eq!(exceptions.error_object_uses.len(), 1);
+}
+
+#[test]
+fn scope_resolution() {
+    let source = "(define (f x)
+  (define y (g x))
+  (set! x y)
+  (lambda (x) (h x y)))
+(begin (define g car))
+(guard (e (#t e)) (receive (a . b) (f e) b))
+(let*-values (((c) 1) ((d) c)) d)";
+    let program = parse(source);
+    let scopes = Scopes::analyze(&program);
+    let bindings = scopes
+        .bindings
+        .iter()
+        .map(|binding| (binding.name.0.as_str(), binding.kind))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        bindings,
+        vec![
+            ("f", BindingKind::TopLevel),
+            ("g", BindingKind::TopLevel),
+            ("x", BindingKind::Parameter),
+            ("y", BindingKind::Internal),
+            ("x", BindingKind::Parameter),
+            ("e", BindingKind::Parameter),
+            ("a", BindingKind::Parameter),
+            ("b", BindingKind::Parameter),
+            ("c", BindingKind::Parameter),
+            ("d", BindingKind::Parameter),
+        ]
+    );
+    let resolved = scopes
+        .references
+        .iter()
+        .map(|reference| {
+            let binding = match reference.resolution {
+                Resolution::Bound(index) => Some(index),
+                Resolution::Free => None,
+            };
+            (reference.name.0.as_str(), binding, reference.assignment)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        resolved,
+        vec![
+            ("g", Some(1), false),
+            ("x", Some(2), false),
+            ("x", Some(2), true),
+            ("y", Some(3), false),
+            ("h", None, false),
+            ("x", Some(4), false),
+            ("y", Some(3), false),
+            ("car", None, false),
+            ("e", Some(5), false),
+            ("f", Some(0), false),
+            // The guarded body is outside of the scope of the condition variable
+            ("e", None, false),
+            ("b", Some(7), false),
+            ("c", Some(8), false),
+            ("d", Some(9), false),
+        ]
+    );
+    let nodes = NodeIndex::new(&program);
+    let offset = source.find("(g x)").unwrap() + 1;
+    assert_eq!(
+        scopes.resolve(nodes.node_at(offset).unwrap()),
+        Some(Resolution::Bound(1))
+    );
+    let offset = source.find("(set! x y)").unwrap();
+    let reference = scopes.reference(nodes.node_at(offset).unwrap()).unwrap();
+    assert!(reference.assignment);
+    assert_eq!(reference.resolution, Resolution::Bound(2));
+    assert_eq!(scopes.binding_at(&(9..10)), Some(0));
+    assert_eq!(scopes.references_to(3).count(), 2);
+    assert_eq!(scopes.free_references().count(), 3);
+}
+
+#[test]
+fn let_star_values_nests_scopes() {
+    let source = "(let*-values (((a b) (values 1 2)) ((a) (+ a b))) a)";
+    let scopes = Scopes::analyze(&parse(source));
+    let bindings = scopes
+        .bindings
+        .iter()
+        .map(|binding| (binding.name.0.as_str(), binding.scope, binding.shadows))
+        .collect::<Vec<_>>();
+    // The top level is the scope 0, every clause nests in the previous one
+    assert_eq!(
+        bindings,
+        vec![("a", 1, None), ("b", 1, None), ("a", 2, Some(0))]
+    );
+    let resolved = scopes
+        .references
+        .iter()
+        .filter(|reference| reference.name.0 != "values" && reference.name.0 != "+")
+        .map(|reference| (reference.name.0.as_str(), reference.resolution))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        resolved,
+        vec![
+            ("a", Resolution::Bound(0)),
+            ("b", Resolution::Bound(1)),
+            ("a", Resolution::Bound(2)),
+        ]
+    );
+}
+
+#[test]
+fn closure_variables() {
+    let source = "(define top 1)
+(define (counter start)
+  (define step 1)
+  (lambda ()
+    (set! start (+ start step top))
+    (lambda () (display start))))
+(counter 0)";
+    let program = parse(source);
+    let closures = Closures::analyze(&program);
+    fn name<'a>(closures: &'a Closures, variable: &'a FreeVariable) -> &'a str {
+        match variable {
+            FreeVariable::Bound(index) => &closures.scopes.bindings[*index].name.0,
+            FreeVariable::Global(name) => name,
+        }
+    }
+    let names = |indices: &[usize]| {
+        indices
+            .iter()
+            .map(|index| closures.scopes.bindings[*index].name.0.as_str())
+            .collect::<Vec<_>>()
+    };
+    let found = closures
+        .closures
+        .iter()
+        .map(|closure| {
+            (
+                &source[closure.span.clone()],
+                closure.parent,
+                closure
+                    .free
+                    .iter()
+                    .map(|variable| name(&closures, variable))
+                    .collect::<Vec<_>>(),
+                names(&closure.captured),
+                names(&closure.mutated),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        found,
+        [
+            (
+                "(counter start)\n  (define step 1)\n  (lambda ()\n    (set! start (+ start step top))\n    (lambda () (display start)))",
+                None,
+                vec!["+", "top", "display"],
+                vec![],
+                vec![],
+            ),
+            (
+                "(lambda ()\n    (set! start (+ start step top))\n    (lambda () (display start)))",
+                Some(0),
+                vec!["start", "+", "step", "top", "display"],
+                vec!["start", "step"],
+                vec!["start"],
+            ),
+            (
+                "(lambda () (display start))",
+                Some(1),
+                vec!["display", "start"],
+                vec!["start"],
+                vec!["start"],
+            ),
+        ]
+    );
+    let start = closures.closures[1].captured[0];
+    assert!(closures.is_boxed(start));
+    assert!(!closures.is_boxed(closures.closures[1].captured[1]));
+
+    let index = NodeIndex::new(&program);
+    let inner = index
+        .node_at(source.find("(lambda () (display").unwrap())
+        .unwrap();
+    assert_eq!(
+        closures.closure(inner).map(|closure| closure.parent),
+        Some(Some(1))
+    );
+}
+
+#[test]
+fn closure_variables_of_generated_and_quoted_code() {
+    // The made up datums of a template share the span of the call site
+    let program = Parser::builder()
+        .with_transformer(
+            "make-adder",
+            SyntaxRules::new().with_rule(
+                Pattern::new("(make-adder)").unwrap(),
+                Template::new("(lambda (n) (lambda (m) (list '(n m) (+ n m))))"),
+            ),
+        )
+        .build()
+        .parse("(make-adder)", "test.scm")
+        .unwrap();
+    let closures = Closures::analyze(&program);
+    let found = closures
+        .closures
+        .iter()
+        .map(|closure| {
+            let names = |indices: &[usize]| {
+                indices
+                    .iter()
+                    .map(|index| closures.scopes.bindings[*index].name.0.clone())
+                    .collect::<Vec<_>>()
+            };
+            (closure.free.len(), names(&closure.captured))
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(found, [(2, vec![]), (3, vec!["n".to_owned()])]);
+}
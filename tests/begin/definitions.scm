@@ -0,0 +1,5 @@
+(begin (define x 1) (define y 2))
+(define (f)
+  (begin (define z 3))
+  (begin (set! z 4) z))
+(f)
@@ -0,0 +1,3 @@
+(define (f)
+  (begin (define x 1) x))
+(f)
@@ -0,0 +1,73 @@
+use scheme_parser::transformer::cond_expand::Features;
+use scheme_parser::*;
+
+fn defined_names(program: &Program) -> Vec<&str> {
+    program
+        .body
+        .as_ref()
+        .unwrap()
+        .defs
+        .iter()
+        .map(|(def, _)| match def {
+            Definition::Variable((name, _), _) => name.as_str(),
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+#[test]
+fn select_by_features() {
+    let source = "(cond-expand
+  ((and r7rs (not chibi)) (define r7rs-not-chibi 1))
+  ((or gauche (library (srfi 1))) (define srfi-1 2))
+  (else (define fallback 0)))
+(cond-expand (chibi (define chibi 1)))
+0";
+    let parse = |features: Features| {
+        Parser::builder()
+            .with_features(features)
+            .build()
+            .parse(source, "test.scm")
+            .unwrap()
+    };
+    let program = parse(Features::new().with_feature("r7rs"));
+    assert_eq!(defined_names(&program), vec!["r7rs-not-chibi"]);
+    let program = parse(
+        Features::new()
+            .with_feature("chibi")
+            .with_library(&["srfi", "1"]),
+    );
+    assert_eq!(defined_names(&program), vec!["srfi-1", "chibi"]);
+    let program = parse(Features::new());
+    assert_eq!(defined_names(&program), vec!["fallback"]);
+}
+
+#[test]
+fn splice_into_library_and_included_file() {
+    let loader = MemoryLoader::new().with_file(
+        "impl.scm",
+        "(cond-expand (chibi (define impl 1)) (else (define impl 0)))\nimpl",
+    );
+    let source = "(define-library (lib)
+  (cond-expand
+    (chibi (import (chibi)))
+    (else (import (scheme base))))
+  (export impl))
+(include \"impl.scm\")
+impl";
+    let program = Parser::builder()
+        .with_source_loader(loader)
+        .with_features(Features::new().with_feature("chibi"))
+        .build()
+        .parse(source, "test.scm")
+        .unwrap();
+    let declarations = &program.libraries[0].0.declarations;
+    assert!(matches!(
+        &declarations[0].0,
+        LibraryDeclaration::Import(sets) if matches!(
+            &sets[0].0,
+            ImportSet::Library(name) if name[0].0 == Primitive::Ident("chibi".into())
+        )
+    ));
+    assert_eq!(declarations.len(), 2);
+}
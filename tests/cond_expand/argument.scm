@@ -0,0 +1 @@
+(display (cond-expand (else 1 2)))
@@ -0,0 +1,6 @@
+(cond-expand
+  ((and r7rs (not chibi)) (define impl 1))
+  ((or gauche (library (srfi 1))) (define impl 2))
+  (else (define impl 0) (define fallback #t)))
+(cond-expand (chibi (display impl)))
+(list impl (cond-expand ((not chibi) fallback)))
@@ -0,0 +1,4 @@
+(cond-expand
+  (else (define impl 0))
+  (chibi (define impl 1)))
+impl
@@ -0,0 +1,4 @@
+(cond-expand
+  ((not chibi gauche) (define impl 1))
+  (else (define impl 0)))
+impl
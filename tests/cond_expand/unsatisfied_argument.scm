@@ -0,0 +1 @@
+(display (cond-expand (chibi 1)))
@@ -0,0 +1,43 @@
+use scheme_parser::*;
+
+fn read(source: &str) -> Spanned<Datum> {
+    read_datum(source, "test.scm").unwrap().0
+}
+
+#[test]
+fn build_datums() {
+    let value = 42;
+    let names = ["a", "b"].map(|name| Primitive::Ident(name.into()));
+    let datum = datum!((define (list->vector! x . rest) (if #t "s" 1.5) #(unquote value) (call/cc null?) (let* () #(unquote-splicing names)) (+ 1 ...)));
+    assert!(datum.same_structure(&read(
+        "(define (list->vector! x . rest) (if #t \"s\" 1.5) 42 (call/cc null?) (let* () a b) (+ 1 ...))"
+    )));
+    let Datum::List(list) = &datum.0 else {
+        panic!("expect a list")
+    };
+    assert_eq!(list[0].0, Datum::Keyword("define"));
+
+    let interpolated = read("(x y)");
+    let datum = datum!((quote #(unquote interpolated.clone()) #f));
+    let Datum::List(list) = datum.0 else {
+        panic!("expect a list")
+    };
+    assert_eq!(list[1], interpolated);
+    assert_eq!(list[2].0, Primitive::Bool(false).into());
+}
+
+#[test]
+fn build_expressions() {
+    let (expr, _) = expr!((lambda (x) (set! x #(unquote -1)) x));
+    let Expression::Procedure { args, body, .. } = expr else {
+        panic!("expect a procedure")
+    };
+    assert_eq!(args[0].0, "x");
+    assert!(matches!(body.exprs[0].0, Expression::Assignment(..)));
+}
+
+#[test]
+#[should_panic(expected = "expr! takes an expression")]
+fn not_an_expression() {
+    expr!((if));
+}
@@ -0,0 +1,4 @@
+(define (receive x) x)
+(lambda (else) else)
+(set! begin 1)
+(receive 1)
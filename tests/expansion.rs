@@ -0,0 +1,337 @@
+use chumsky::prelude::Simple;
+use scheme_parser::transformer::{cond_expand::Features, Context, Transformer};
+use scheme_parser::*;
+
+/// Rewrite `(from args ...)` into `(to args ...)`
+struct Rename(&'static str, &'static str);
+
+impl Transformer for Rename {
+    fn transform(
+        &self,
+        (datum, _): &mut Spanned<Datum>,
+        _: &mut Context,
+    ) -> Result<bool, Box<Simple<Token>>> {
+        Ok(match datum {
+            Datum::List(list) => match list.first_mut() {
+                Some((Datum::Primitive(Primitive::Ident(head)), _)) if head == self.0 => {
+                    *head = self.1.into();
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        })
+    }
+}
+
+/// Rewrite `(nest)` into `(list (nest))`
+struct Nest;
+
+impl Transformer for Nest {
+    fn transform(
+        &self,
+        (datum, span): &mut Spanned<Datum>,
+        _: &mut Context,
+    ) -> Result<bool, Box<Simple<Token>>> {
+        Ok(match datum {
+            Datum::List(list)
+                if matches!(
+                    list.as_slice(),
+                    [(Datum::Primitive(Primitive::Ident(head)), _)] if head == "nest"
+                ) =>
+            {
+                let nested = (std::mem::take(list).into(), span.clone());
+                *list = vec![
+                    (Primitive::Ident("list".into()).into(), span.clone()),
+                    nested,
+                ];
+                true
+            }
+            _ => false,
+        })
+    }
+}
+
+/// Rewrite `(once expr)` into `((lambda (tmp) tmp) expr)`
+struct Once;
+
+impl Transformer for Once {
+    fn transform(
+        &self,
+        (datum, span): &mut Spanned<Datum>,
+        context: &mut Context,
+    ) -> Result<bool, Box<Simple<Token>>> {
+        let Datum::List(list) = datum else {
+            return Ok(false);
+        };
+        if !matches!(list.first(), Some((Datum::Primitive(Primitive::Ident(head)), _)) if head == "once")
+        {
+            return Ok(false);
+        }
+        let mut args = list.drain(1..);
+        let expr = args
+            .next()
+            .ok_or_else(|| Simple::custom(span.clone(), "Expect an expression"))?;
+        if let Some((_, extra)) = args.next() {
+            context.warning(Simple::custom(extra, "Extra expressions are ignored"));
+        }
+        drop(args);
+        let tmp: Spanned<Datum> = (Primitive::Ident(context.gensym("tmp")).into(), span.clone());
+        let lambda = vec![
+            (Datum::Keyword("lambda"), span.clone()),
+            (Datum::List(vec![tmp.clone()]), span.clone()),
+            tmp,
+        ];
+        *list = vec![(Datum::List(lambda), span.clone()), expr];
+        Ok(true)
+    }
+}
+
+/// Rewrite `(broken)` into `(if)`
+struct Broken;
+
+impl Transformer for Broken {
+    fn transform(
+        &self,
+        (datum, span): &mut Spanned<Datum>,
+        _: &mut Context,
+    ) -> Result<bool, Box<Simple<Token>>> {
+        Ok(match datum {
+            Datum::List(list)
+                if matches!(
+                    list.as_slice(),
+                    [(Datum::Primitive(Primitive::Ident(head)), _)] if head == "broken"
+                ) =>
+            {
+                *list = vec![(Datum::Keyword("if"), span.clone())];
+                true
+            }
+            _ => false,
+        })
+    }
+}
+
+#[test]
+fn expand_nested_until_fixed_point() {
+    let mut parser = Parser::builder()
+        .with_transformer("my-begin", Rename("my-begin", "inner-begin"))
+        .with_transformer("inner-begin", Rename("inner-begin", "begin"))
+        .build();
+    let program = parser
+        .parse("(lambda () (my-begin 1 (my-begin 2 3)))", "test.scm")
+        .unwrap();
+    let Expression::Procedure { body, .. } = &program.body.unwrap().last_expr.0 else {
+        panic!("expect a procedure")
+    };
+    let Expression::Begin { exprs, .. } = &body.last_expr.0 else {
+        panic!("expect a begin")
+    };
+    assert!(matches!(&exprs[1].0, Expression::Begin { .. }));
+}
+
+#[test]
+fn runaway_expansion() {
+    let error = |mut parser: Parser| {
+        parser
+            .parse("(display (nest))", "test.scm")
+            .unwrap_err()
+            .with_color(false)
+            .to_string()
+    };
+    let message = error(
+        Parser::builder()
+            .with_transformer("nest", Rename("nest", "nest"))
+            .build(),
+    );
+    assert!(
+        message.contains("Expansion exceeds the depth limit of 128"),
+        "{}",
+        message
+    );
+    assert!(message.contains("[test.scm:1:10]"), "{}", message);
+
+    let message = error(
+        Parser::builder()
+            .with_transformer("nest", Nest)
+            .with_expansion_limit(8)
+            .build(),
+    );
+    assert!(
+        message.contains("Expansion exceeds the depth limit of 8"),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn transformer_errors_with_parser_errors() {
+    let mut parser = Parser::builder().with_transformer("once", Once).build();
+    let message = parser
+        .parse("(display (once) (once 1 2))\n(if)", "test.scm")
+        .unwrap_err()
+        .with_color(false)
+        .to_string();
+    assert!(
+        message.contains("Error: Expect an expression"),
+        "{}",
+        message
+    );
+    assert!(message.contains("[test.scm:1:10]"), "{}", message);
+    assert!(
+        message.contains("Unexpected token in input while parsing if"),
+        "{}",
+        message
+    );
+    assert!(
+        message.contains("Warning: Extra expressions are ignored"),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn transformer_context() {
+    let mut parser = Parser::builder().with_transformer("once", Once).build();
+    let program = parser.parse("(once (once 1 2))", "test.scm").unwrap();
+    let warnings = parser.warnings().unwrap().with_color(false).to_string();
+    assert!(
+        warnings.contains("Warning: Extra expressions are ignored"),
+        "{}",
+        warnings
+    );
+    let Expression::ProcedureCall { operator, args } = &program.body.unwrap().last_expr.0 else {
+        panic!("expect a procedure call")
+    };
+    let Expression::Procedure { args: formals, .. } = &operator.0 else {
+        panic!("expect a procedure")
+    };
+    let Expression::ProcedureCall { operator, .. } = &args[0].0 else {
+        panic!("expect a procedure call")
+    };
+    let Expression::Procedure { args: inner, .. } = &operator.0 else {
+        panic!("expect a procedure")
+    };
+    assert_eq!(formals[0].0, "tmp#1");
+    assert_eq!(inner[0].0, "tmp#2");
+
+    let features = Features::new().with_feature("r7rs");
+    let context = Context::new("test.scm", &features);
+    assert_eq!(context.source_path(), "test.scm");
+    assert!(context.features().has_feature("r7rs"));
+}
+
+#[test]
+fn register_and_order_transformers() {
+    let builder = Parser::builder()
+        .with_transformer("nest", Nest)
+        .with_transformer("once", Once)
+        .with_transformer_before("nest", "rename", Rename("a", "b"))
+        .with_transformer("nest", Rename("nest", "list"))
+        .without_builtin("include")
+        .unwrap();
+    assert_eq!(
+        Parser::builder()
+            .without_builtin("begin")
+            .err()
+            .unwrap()
+            .to_string(),
+        "Unknown builtin begin, expect one of cond-expand, include"
+    );
+    assert_eq!(
+        builder.transformers().collect::<Vec<_>>(),
+        vec!["cond-expand", "rename", "nest", "once"]
+    );
+    let mut parser = builder.without_transformer("once").build();
+    assert_eq!(
+        parser.transformers().collect::<Vec<_>>(),
+        vec!["cond-expand", "rename", "nest"]
+    );
+    // `nest` is replaced, so it does not run away
+    parser.parse("(nest)", "test.scm").unwrap();
+    let message = parser
+        .parse("(include \"a.scm\")", "test.scm")
+        .unwrap_err()
+        .with_color(false)
+        .to_string();
+    assert!(message.contains("Unexpected token include"), "{}", message);
+
+    let builder = Parser::builder()
+        .with_transformer("nest", Nest)
+        .with_builtins(&["include", "cond-expand", "include"])
+        .unwrap();
+    assert_eq!(
+        builder.transformers().collect::<Vec<_>>(),
+        vec!["include", "cond-expand", "nest"]
+    );
+}
+
+#[test]
+fn expansion_provenance() {
+    let mut parser = Parser::builder()
+        .with_transformer("broken", Broken)
+        .with_transformer("twice", Rename("twice", "broken"))
+        .with_transformer("once", Once)
+        .build();
+    let message = parser
+        .parse("(display\n  (twice))", "test.scm")
+        .unwrap_err()
+        .with_color(false)
+        .to_string();
+    assert!(
+        message.contains("Unexpected token in input while parsing if"),
+        "{}",
+        message
+    );
+    assert!(
+        message.contains("in expansion of macro broken"),
+        "{}",
+        message
+    );
+    assert!(
+        message.contains("in expansion of macro twice"),
+        "{}",
+        message
+    );
+    let backtrace = parser.expansions().backtrace(&(17..18));
+    assert_eq!(
+        backtrace
+            .iter()
+            .map(|expansion| (expansion.transformer.as_str(), expansion.call_site.clone()))
+            .collect::<Vec<_>>(),
+        vec![("broken", 11..18), ("twice", 11..18)]
+    );
+
+    // The expression of `once` is written in the source, not made up by `once`
+    let message = parser
+        .parse("(once (if))", "test.scm")
+        .unwrap_err()
+        .with_color(false)
+        .to_string();
+    assert!(!message.contains("in expansion of macro"), "{}", message);
+}
+
+#[test]
+fn builtins_and_transformers_expand_together() {
+    let loader = MemoryLoader::new().with_file("a.scm", "(define y x)\n(my-begin 1 2)");
+    let mut parser = Parser::builder()
+        .with_transformer("my-include", Rename("my-include", "include"))
+        .with_transformer("my-begin", Rename("my-begin", "begin"))
+        .with_transformer("broken", Broken)
+        .with_source_loader(loader)
+        .build();
+    let program = parser
+        .parse("(define (f x) (my-include \"a.scm\"))\n(f 1)", "test.scm")
+        .unwrap();
+    let body = program.body.unwrap();
+    let Definition::Variable(_, (Expression::Procedure { body: f, .. }, _)) = &body.defs[0].0
+    else {
+        panic!("expect a procedure")
+    };
+    assert_eq!(f.defs.len(), 1);
+    assert!(matches!(&f.last_expr.0, Expression::Begin { .. }));
+
+    // Quoted data is not code, so it is not expanded
+    parser
+        .parse("(display '(broken (my-include \"b.scm\")))", "test.scm")
+        .unwrap();
+}
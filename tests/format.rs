@@ -0,0 +1,71 @@
+use scheme_parser::*;
+
+fn format(source: &str, width: usize) -> String {
+    let formatter = Formatter::new().with_width(width);
+    let formatted = formatter.format(source, "test.scm").unwrap();
+    assert_eq!(formatter.format(&formatted, "test.scm").unwrap(), formatted);
+    formatted
+}
+
+#[test]
+fn indent_forms() {
+    let source = "(define (f xs) (let loop ((xs xs) (acc 0)) (if (null? xs) acc (loop (cdr xs) (+ acc (car xs))))))";
+    assert_eq!(
+        format(source, 40),
+        "(define (f xs)
+  (let loop ((xs xs) (acc 0))
+    (if (null? xs)
+        acc
+        (loop (cdr xs)
+              (+ acc (car xs))))))
+"
+    );
+    assert_eq!(format(source, 100), format!("{}\n", source));
+    assert_eq!(
+        format("(lambda   (x)\n\n\n x)  (f 1\n2)", 80),
+        "(lambda (x)\n\n  x)\n(f 1 2)\n"
+    );
+}
+
+#[test]
+fn keep_comments() {
+    let source = "  ; header
+
+
+(define x ; trailing
+; own line
+1)
+'(a ; in a quote
+  b)
+(f) ; last
+";
+    assert_eq!(
+        format(source, 80),
+        "; header
+
+(define x ; trailing
+  ; own line
+  1)
+'(a ; in a quote
+  b)
+(f) ; last
+"
+    );
+    assert_eq!(format("; only a comment", 80), "; only a comment\n");
+    assert_eq!(format("", 80), "");
+}
+
+#[test]
+fn fill_data() {
+    assert_eq!(
+        format("'(1 2 3 4 5 6 7 8 9 10 11 12)", 16),
+        "'(1 2 3 4 5 6 7
+  8 9 10 11 12)
+"
+    );
+}
+
+#[test]
+fn format_error() {
+    assert!(Formatter::new().format("(f", "test.scm").is_err());
+}
@@ -0,0 +1,3 @@
+(guard (e (else 1)
+          ((number? e) e))
+  (raise 1))
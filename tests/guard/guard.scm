@@ -0,0 +1,7 @@
+(guard (e ((symbol? e) (display e))
+          ((error-object? e) => error-object-message)
+          (else unknown))
+  (define x 1)
+  (raise x))
+(guard (e ((number? e)))
+  (error failed 42))
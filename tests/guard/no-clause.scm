@@ -0,0 +1,2 @@
+(guard (e)
+  (raise 1))
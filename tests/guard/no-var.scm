@@ -0,0 +1,2 @@
+(guard ((e) (else 1))
+  (raise 1))
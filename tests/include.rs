@@ -0,0 +1,143 @@
+use scheme_parser::*;
+
+fn parse(loader: MemoryLoader, source: &str) -> (Parser, Result<Program, TokenizeOrParseError>) {
+    let mut parser = Parser::builder().with_source_loader(loader).build();
+    let result = parser.parse(source, "main.scm");
+    (parser, result)
+}
+
+fn error_message(result: Result<Program, TokenizeOrParseError>) -> String {
+    result.unwrap_err().with_color(false).to_string()
+}
+
+#[test]
+fn include_records_source_file() {
+    let loader = MemoryLoader::new()
+        .with_file("lib/a.scm", "(include \"b.scm\")\n(define a 1)\n(+ a b)")
+        .with_file("lib/b.scm", "(define b 2)");
+    let (parser, result) = parse(loader, "(include \"lib/a.scm\")\n(display a)");
+    let program = result.unwrap();
+    let paths = parser
+        .sources()
+        .files()
+        .iter()
+        .map(|file| file.path.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(paths, vec!["main.scm", "lib/a.scm", "lib/b.scm"]);
+
+    // The included definitions are spliced into the top level
+    let body = program.body.unwrap();
+    let spans = body
+        .defs
+        .iter()
+        .map(|(def, _)| match def {
+            Definition::Variable((name, span), _) => {
+                let (file, local) = parser.sources().locate(span).unwrap();
+                (name.as_str(), file.path.as_str(), &file.source[local])
+            }
+            _ => unreachable!(),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        spans,
+        vec![("b", "lib/b.scm", "b"), ("a", "lib/a.scm", "a")]
+    );
+}
+
+#[test]
+fn include_ci_folds_case() {
+    let loader = MemoryLoader::new().with_file("upper.scm", "(DEFINE Foo \"Bar\")\n(IF Foo Foo)");
+    let (_, result) = parse(loader, "(include-ci \"upper.scm\")");
+    let body = result.unwrap().body.unwrap();
+    assert!(matches!(&body.defs[0].0, Definition::Variable((name, _), _) if name == "foo"));
+    assert!(matches!(&body.last_expr.0, Expression::Conditional { .. }));
+}
+
+#[test]
+fn include_cycle() {
+    let loader = MemoryLoader::new()
+        .with_file("a.scm", "(include \"b.scm\")")
+        .with_file("b.scm", "(define b 1)\n(include \"a.scm\")");
+    let (_, result) = parse(loader, "(include \"a.scm\")\nb");
+    let message = error_message(result);
+    assert!(
+        message.contains("Include cycle: main.scm -> a.scm -> b.scm -> a.scm"),
+        "{}",
+        message
+    );
+    assert!(message.contains("[b.scm:2:10]"), "{}", message);
+}
+
+#[test]
+fn include_missing_file() {
+    let (_, result) = parse(MemoryLoader::new(), "(include \"missing.scm\")\n1");
+    let message = error_message(result);
+    assert!(
+        message.contains("Failed to load 'missing.scm'"),
+        "{}",
+        message
+    );
+    assert!(message.contains("[main.scm:1:10]"), "{}", message);
+
+    // The expansion goes on after a file which could not be loaded
+    let loader = MemoryLoader::new().with_file("bad.scm", "(lambda (x 1) x)");
+    let (_, result) = parse(
+        loader,
+        "(display (include \"missing.scm\"))\n(include \"bad.scm\")\n1",
+    );
+    let message = error_message(result);
+    assert!(
+        message.contains("Failed to load 'missing.scm'"),
+        "{}",
+        message
+    );
+    assert!(message.contains("[bad.scm:1:12]"), "{}", message);
+    assert!(!message.contains("expands to nothing"), "{}", message);
+
+    let (_, result) = parse(
+        MemoryLoader::new(),
+        "(include (string-append \"a\" \"b\"))\n1",
+    );
+    let message = error_message(result);
+    assert!(
+        message.contains("Expect a string as the file to include, found (string-append ...)"),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn include_reports_errors_in_included_file() {
+    let loader = MemoryLoader::new().with_file("bad.scm", "(lambda (x 1) x)");
+    let (_, result) = parse(loader, "(include \"bad.scm\")");
+    let message = error_message(result);
+    assert!(message.contains("[bad.scm:1:12]"), "{}", message);
+}
+
+#[test]
+fn include_in_nested_positions() {
+    let loader = MemoryLoader::new()
+        .with_file("body.scm", "(define y x)\n(* y 2)")
+        .with_file("expr.scm", "1 2")
+        .with_file("empty.scm", "");
+    let (_, result) = parse(
+        loader,
+        "(include \"empty.scm\")
+(define (h x) (include \"body.scm\"))
+(display (include \"expr.scm\"))",
+    );
+    let body = result.unwrap().body.unwrap();
+    let Definition::Variable(_, (Expression::Procedure { body: h, .. }, _)) = &body.defs[0].0
+    else {
+        panic!("expect a procedure")
+    };
+    assert_eq!(h.defs.len(), 1);
+    assert!(matches!(&h.last_expr.0, Expression::ProcedureCall { .. }));
+    let Expression::ProcedureCall { args, .. } = &body.last_expr.0 else {
+        panic!("expect a call")
+    };
+    let Expression::Begin { exprs, .. } = &args[0].0 else {
+        panic!("expect a begin")
+    };
+    assert_eq!(exprs.len(), 2);
+}
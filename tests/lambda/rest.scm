@@ -0,0 +1,3 @@
+(define (f x . rest) x)
+(define (g . rest) rest)
+(lambda (x . rest) rest)
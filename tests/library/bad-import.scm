@@ -0,0 +1,2 @@
+(import (only (scheme base) car 3))
+(car x)
@@ -0,0 +1,2 @@
+(define-library (example -1)
+  (export foo))
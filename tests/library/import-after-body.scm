@@ -0,0 +1,3 @@
+(define x 1)
+(import (scheme base))
+x
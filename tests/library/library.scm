@@ -0,0 +1,13 @@
+(define-library (example stack 1)
+  (export make-stack push! (rename stack-pop pop!))
+  (import (scheme base)
+          (only (scheme write) display write)
+          (prefix (except (srfi 1) delete) list-)
+          (rename (scheme char) (char-upcase upcase)))
+  (include "stack-impl.scm" "stack-util.scm")
+  (include-ci "legacy.scm")
+  (include-library-declarations "common.sld")
+  (begin
+    (define (make-stack) (list))
+    (define (push! stack x) (cons x stack))
+    (define stack-pop cdr)))
@@ -0,0 +1,4 @@
+(import (scheme base) (example stack 1))
+(import (scheme write))
+(define s (make-stack))
+(display "stack: \"s\"\n")
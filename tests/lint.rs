@@ -0,0 +1,212 @@
+use scheme_parser::lint::*;
+use scheme_parser::transformer::pattern::{Pattern, SyntaxRules, Template};
+use scheme_parser::*;
+
+const SOURCE: &str = "(define (f x x)
+  (define unused 1)
+  (define _ignored 2)
+  (let-values (((y) (g (if x 1))))
+    (set! z 2)
+    (if y (display y))
+    (lambda (x) y)))
+(define f 2)
+(f 1 2)";
+
+fn lint(linter: &Linter, source: &str) -> Vec<(&'static str, String)> {
+    let program = Parser::default().parse(source, "test.scm").unwrap();
+    linter
+        .check(&program)
+        .into_iter()
+        .map(|diagnostic| (diagnostic.code, source[diagnostic.span].to_owned()))
+        .collect()
+}
+
+#[test]
+fn builtin_lints() {
+    assert_eq!(
+        lint(&Linter::new(), SOURCE),
+        [
+            ("unused-parameter", "x"),
+            ("duplicate-parameter", "x"),
+            ("unused-variable", "unused"),
+            ("if-without-alternative", "(if x 1)"),
+            ("unbound-assignment", "z"),
+            ("unused-parameter", "x"),
+            ("shadowed-binding", "x"),
+            ("duplicate-definition", "f"),
+        ]
+        .map(|(code, source)| (code, source.to_owned()))
+    );
+}
+
+struct CallsDisplay;
+
+impl Lint for CallsDisplay {
+    fn code(&self) -> &'static str {
+        "calls-display"
+    }
+    fn default_severity(&self) -> Severity {
+        Severity::Error
+    }
+    fn check(&self, context: &LintContext) -> Vec<Diagnostic> {
+        context
+            .scopes
+            .free_references()
+            .filter(|reference| reference.name.0 == "display")
+            .map(|reference| Diagnostic::new(reference.name.1.clone(), "Do not display"))
+            .collect()
+    }
+}
+
+#[test]
+fn configure_lints() {
+    let linter = Linter::empty()
+        .with_lint(CallsDisplay)
+        .with_lint(lint::builtin::UnusedVariable)
+        .with_severity("unused-variable", Severity::Allow);
+    assert_eq!(
+        linter.codes().collect::<Vec<_>>(),
+        ["calls-display", "unused-variable"]
+    );
+    assert_eq!(
+        lint(&linter, SOURCE),
+        [("calls-display", "display".to_owned())]
+    );
+
+    let mut parser = Parser::default();
+    let program = parser.parse(SOURCE, "test.scm").unwrap();
+    let report =
+        LintReport::new(linter.check(&program), parser.sources().clone()).with_color(false);
+    assert!(report.has_errors());
+    let rendered = report.to_string();
+    assert!(
+        rendered.contains("[calls-display] Error: Do not display"),
+        "{}",
+        rendered
+    );
+
+    let report =
+        LintReport::new(Linter::new().check(&program), parser.sources().clone()).with_color(false);
+    assert!(!report.has_errors());
+    let rendered = report.to_string();
+    assert!(
+        rendered.contains("[duplicate-definition] Warning: `f` is defined again"),
+        "{}",
+        rendered
+    );
+    assert!(rendered.contains("First defined here"), "{}", rendered);
+}
+
+#[test]
+fn arity_mismatch() {
+    let source = "(define (two a b) a)
+(define (rest a . r) r)
+(define any (lambda x x))
+(define (replaced) 1)
+(define (f) (define (two) 2) (two 1))
+(set! replaced (lambda (x) x))
+(two 1)
+(two 1 2)
+(rest)
+(rest 1 2 3)
+(any)
+(replaced 1)
+(display '(two 1 2 3))
+(f)";
+    let linter = Linter::empty().with_lint(lint::builtin::ArityMismatch);
+    let program = Parser::default().parse(source, "test.scm").unwrap();
+    let diagnostics = linter.check(&program);
+    let found = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            (
+                &source[diagnostic.span.clone()],
+                diagnostic.message.as_str(),
+                diagnostic.severity,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        found,
+        [
+            (
+                "(two 1)",
+                "`two` takes 0 arguments but 1 is given",
+                Severity::Error
+            ),
+            (
+                "(two 1)",
+                "`two` takes 2 arguments but 1 is given",
+                Severity::Error
+            ),
+            (
+                "(rest)",
+                "`rest` takes at least 1 argument but 0 are given",
+                Severity::Error
+            ),
+        ]
+    );
+    let definitions = diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.labels[0].1.start)
+        .collect::<Vec<_>>();
+    assert_eq!(definitions, [source.find("(two) 2").unwrap() + 1, 9, 30]);
+
+    // The made up datums of a template share the span of the call site
+    let program = Parser::builder()
+        .with_transformer(
+            "define-both",
+            SyntaxRules::new().with_rule(
+                Pattern::new("(define-both)").unwrap(),
+                Template::new("(begin (define (none) 0) (define (one a) a) (none 1) (one 1))"),
+            ),
+        )
+        .build()
+        .parse("(define-both)", "test.scm")
+        .unwrap();
+    let messages = linter
+        .check(&program)
+        .into_iter()
+        .map(|diagnostic| diagnostic.message)
+        .collect::<Vec<_>>();
+    assert_eq!(messages, ["`none` takes 0 arguments but 1 is given"]);
+}
+
+#[test]
+fn non_tail_recursion() {
+    let source = "(define (count n)
+  (if (= n 0) 0 (+ 1 (count (- n 1)))))
+(define (loop n)
+  (when (> n 0) (display n) (loop (- n 1))))
+(define (walk tree)
+  (map (lambda (child) (walk child)) tree))
+(count 3)";
+    let linter = Linter::new().with_severity("non-tail-recursion", Severity::Warning);
+    let program = Parser::builder()
+        .with_transformer(
+            "when",
+            SyntaxRules::new().with_rule(
+                Pattern::new("(when test body ...)").unwrap(),
+                Template::new("(if test (begin body ...))"),
+            ),
+        )
+        .build()
+        .parse(source, "test.scm")
+        .unwrap();
+    let diagnostics = linter
+        .check(&program)
+        .into_iter()
+        .filter(|diagnostic| diagnostic.code == "non-tail-recursion")
+        .map(|diagnostic| (source[diagnostic.span].to_owned(), diagnostic.message))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        diagnostics,
+        [(
+            "(count (- n 1))".to_owned(),
+            "Recursive call to `count` is not in tail position".to_owned()
+        )]
+    );
+    assert!(!lint(&Linter::new(), source)
+        .iter()
+        .any(|(code, _)| *code == "non-tail-recursion"));
+}
@@ -0,0 +1,90 @@
+use scheme_parser::transformer::pattern::{Pattern, SyntaxRules, Template};
+use scheme_parser::*;
+
+#[test]
+fn node_index() {
+    let source = "(define (f x) (g x 1))\n(f 2)";
+    let program = Parser::default().parse(source, "test.scm").unwrap();
+    let index = NodeIndex::new(&program);
+    assert_eq!(index.len(), 9);
+
+    let offset = source.find("1)").unwrap();
+    let one = index.node_at(offset).unwrap();
+    assert_eq!(&source[index.node(one).span().clone()], "1");
+    let ancestors = index
+        .ancestors(one)
+        .map(|id| &source[index.node(id).span().clone()])
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ancestors,
+        ["(g x 1)", "(f x) (g x 1)", "(define (f x) (g x 1))"]
+    );
+
+    let body = program.body.as_ref().unwrap();
+    let def = index.id(&body.defs[0]).unwrap();
+    assert_eq!(def, NodeId(0));
+    assert_eq!(index.parent(def), None);
+    assert_eq!(index.children(def), [NodeId(1)]);
+    assert_eq!(index.id(&body.last_expr), Some(NodeId(6)));
+    assert_eq!(index.node_at(source.len()), None);
+
+    // The index only gives ids for the nodes of the program it was built for
+    let clone = program.clone();
+    assert_eq!(index.id(&clone.body.as_ref().unwrap().defs[0]), None);
+    assert!(index.indexes(&program) && !index.indexes(&clone));
+    assert!(analysis::scope::Scopes::with_nodes(&program, &index).is_ok());
+    assert_eq!(
+        analysis::scope::Scopes::with_nodes(&clone, &index).unwrap_err(),
+        ForeignIndex
+    );
+}
+
+#[test]
+fn unique_ids_for_colliding_spans() {
+    let program = Parser::builder()
+        .with_transformer(
+            "twice",
+            SyntaxRules::new().with_rule(
+                Pattern::new("(twice e)").unwrap(),
+                Template::new("(begin e e)"),
+            ),
+        )
+        .build()
+        .parse("(twice (f))", "test.scm")
+        .unwrap();
+    let index = NodeIndex::new(&program);
+    let Expression::Begin { exprs, .. } = &program.body.as_ref().unwrap().last_expr.0 else {
+        panic!("expect a begin")
+    };
+    assert_eq!(exprs[0], exprs[1]);
+    let (first, second) = (index.id(&exprs[0]).unwrap(), index.id(&exprs[1]).unwrap());
+    assert_ne!(first, second);
+    assert_eq!(index.parent(first), index.parent(second));
+}
+
+#[test]
+fn tail_positions() {
+    let source = "(define (f x)
+  (g x)
+  (if (h x)
+      (begin (g 1) (g 2))
+      (let-values (((y) (g 3))) (guard (e (#t (g 4))) (g 5)) (g 6))))
+(g 7)";
+    let program = Parser::default().parse(source, "test.scm").unwrap();
+    let index = NodeIndex::new(&program);
+    let tails = (0..index.len())
+        .map(NodeId)
+        .filter(|id| index.is_tail(*id))
+        .map(|id| &source[index.node(id).span().clone()])
+        .collect::<Vec<_>>();
+    assert_eq!(
+        tails,
+        [
+            "(if (h x)\n      (begin (g 1) (g 2))\n      (let-values (((y) (g 3))) (guard (e (#t (g 4))) (g 5)) (g 6)))",
+            "(begin (g 1) (g 2))",
+            "(g 2)",
+            "(let-values (((y) (g 3))) (guard (e (#t (g 4))) (g 5)) (g 6))",
+            "(g 6)",
+        ]
+    );
+}
@@ -0,0 +1,138 @@
+use chumsky::prelude::Simple;
+use scheme_parser::transformer::{
+    pattern::{Pattern, SyntaxRules, Template},
+    Context, Transformer,
+};
+use scheme_parser::*;
+
+fn read(source: &str) -> Spanned<Datum> {
+    transformer::datumize(&tokenize(source, "test.scm").unwrap(), source, "test.scm")
+        .unwrap()
+        .remove(0)
+}
+
+#[test]
+fn match_with_ellipsis() {
+    let pattern = Pattern::new("(my-let ((name value) ...) body1 body2 ...)").unwrap();
+    let bindings = pattern
+        .matches(&read("(my-let ((a 1) (b 2)) a b)"))
+        .unwrap();
+    let names = bindings.get_all("name").unwrap();
+    assert_eq!(names[1].0, Primitive::Ident("b".into()).into());
+    assert_eq!(names[1].1, 16..17);
+    assert_eq!(bindings.get("body1").unwrap().1, 22..23);
+    assert_eq!(bindings.get_all("body2").unwrap().len(), 1);
+
+    assert!(pattern.matches(&read("(my-let ((a 1) (b)) a)")).is_none());
+    assert!(pattern.matches(&read("(let ((a 1)) a)")).is_none());
+    assert!(pattern.matches(&read("(my-let ())")).is_none());
+    assert_eq!(pattern.head(), Some("my-let"));
+
+    let pattern =
+        Pattern::with_literals("(my-cond (test expr) ... (else last))", &["else"]).unwrap();
+    assert!(pattern.matches(&read("(my-cond (a 1) (else 2))")).is_some());
+    assert!(pattern.matches(&read("(my-cond (a 1) (b 2))")).is_none());
+}
+
+#[test]
+fn reject_duplicate_variables() {
+    let error = Pattern::new("(m (x x) ...)").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        Simple::<Token>::custom(6..7, "Pattern variable x appears more than once").to_string()
+    );
+    // A literal is not a variable, it could appear more than once
+    let pattern = Pattern::with_literals("(m (=> x) (=> y))", &["=>"]).unwrap();
+    assert!(pattern.matches(&read("(m (=> 1) (=> 2))")).is_some());
+}
+
+#[test]
+fn expand_template() {
+    let source = "(my-let ((a 1) (b 2)) (+ a b))";
+    let bindings = Pattern::new("(my-let ((name value) ...) body ...)")
+        .unwrap()
+        .matches(&read(source))
+        .unwrap();
+    let template = Template::new("((lambda (name ...) body ...) value ...)");
+    let (datum, span) = template.expand(&bindings, &(0..source.len())).unwrap();
+    assert_eq!(span, 0..source.len());
+    let Datum::List(list) = datum else {
+        panic!("expect a list")
+    };
+    assert_eq!(list.len(), 3);
+    assert_eq!(list[2].1, 18..19);
+
+    let error = Template::new("(name)")
+        .expand(&bindings, &(0..source.len()))
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        Simple::<Token>::custom(
+            0..source.len(),
+            "Pattern variable name should be followed by an ellipsis"
+        )
+        .to_string()
+    );
+}
+
+/// Rewrite `(swap! a b)` into a `let` through a fresh temporary
+struct Swap;
+
+impl Transformer for Swap {
+    fn transform(
+        &self,
+        datum: &mut Spanned<Datum>,
+        context: &mut Context,
+    ) -> Result<bool, Box<Simple<Token>>> {
+        let Some(mut bindings) = datum_pattern!("(swap a b)").matches(datum) else {
+            return Ok(false);
+        };
+        bindings.insert(
+            "tmp",
+            (
+                Primitive::Ident(context.gensym("tmp")).into(),
+                datum.1.clone(),
+            ),
+        );
+        *datum = datum_template!("((lambda (tmp) (set! a b) (set! b tmp)) a)")
+            .expand(&bindings, &datum.1)?;
+        Ok(true)
+    }
+}
+
+#[test]
+fn write_transformers() {
+    let mut parser = Parser::builder()
+        .with_transformer("swap", Swap)
+        .with_transformer(
+            "my-let",
+            SyntaxRules::new().with_rule(
+                Pattern::new("(my-let ((name value) ...) body ...)").unwrap(),
+                Template::new("((lambda (name ...) body ...) value ...)"),
+            ),
+        )
+        .build();
+    let program = parser
+        .parse("(my-let ((x 1) (y 2)) (swap x y) x)", "test.scm")
+        .unwrap();
+    let Expression::ProcedureCall { operator, args } = &program.body.unwrap().last_expr.0 else {
+        panic!("expect a procedure call")
+    };
+    assert_eq!(args.len(), 2);
+    let Expression::Procedure { args, body, .. } = &operator.0 else {
+        panic!("expect a procedure")
+    };
+    assert_eq!(args.len(), 2);
+    assert_eq!(body.exprs.len(), 1);
+
+    let message = parser
+        .parse("(my-let ((x 1)))", "test.scm")
+        .unwrap_err()
+        .with_color(false)
+        .to_string();
+    assert!(
+        message.contains("in expansion of macro my-let"),
+        "{}",
+        message
+    );
+}
@@ -0,0 +1,4 @@
+(define (quote x) x)
+(display (quote (lambda (x) x)))
+(quote)
+(quote a b)
@@ -0,0 +1,66 @@
+use scheme_parser::*;
+
+#[test]
+fn read_datums_one_by_one() {
+    let source = "(a 'b) ; first\n  #t\n  \"λ\" ; last\n";
+    let (datum, rest) = read_datum(source, "test.scm").unwrap();
+    assert_eq!(datum.1, 0..6);
+    let Datum::List(list) = datum.0 else {
+        panic!("expect a list")
+    };
+    assert_eq!(
+        list[1],
+        (
+            Datum::List(vec![
+                (Primitive::Ident("quote".into()).into(), 3..4),
+                (Primitive::Ident("b".into()).into(), 4..5),
+            ]),
+            3..5
+        )
+    );
+    assert_eq!(rest, "#t\n  \"λ\" ; last\n");
+    let (datum, rest) = read_datum(rest, "test.scm").unwrap();
+    assert_eq!(datum, (Primitive::Bool(true).into(), 0..2));
+    let (datum, rest) = read_datum(rest, "test.scm").unwrap();
+    assert_eq!(datum, (Primitive::String("λ".into()).into(), 0..3));
+    assert_eq!(rest, "");
+}
+
+#[test]
+fn read_errors() {
+    let message = |source| {
+        read_datum(source, "test.scm")
+            .unwrap_err()
+            .with_color(false)
+            .to_string()
+    };
+    assert!(message("(a (b)").contains("Unclosed delimiter ("));
+    assert!(message(")").contains("Unexpected token )"));
+    assert!(message("'").contains("Unexpected end of input"));
+    assert!(message(" ; nothing\n").contains("Unexpected end of input"));
+    // Only the first datum is read, the errors after it are left to the rest
+    assert!(read_datum("(a) )", "test.scm").is_ok());
+    let (datum, rest) = read_datum("(a) {", "test.scm").unwrap();
+    assert_eq!(datum.1, 0..3);
+    assert_eq!(rest, "{");
+    assert!(message(rest).contains("Unexpected char {"));
+}
+
+#[test]
+fn read_all_datums() {
+    let datums = read_all("(a . b) ,c\n'()", "test.scm").unwrap();
+    assert_eq!(datums.len(), 3);
+    assert!(read_all("(a", "test.scm").is_err());
+}
+
+#[test]
+fn quoted_datum_is_data() {
+    let (datum, _) = read_datum("'(lambda (x) x)", "test.scm").unwrap();
+    let (expr, span) = convert_expression(datum).unwrap();
+    assert_eq!(span, 0..15);
+    let Expression::Quote((Datum::List(list), span)) = expr else {
+        panic!("expect a quoted list")
+    };
+    assert_eq!(span, 1..15);
+    assert_eq!(list[0].0, Datum::Keyword("lambda"));
+}
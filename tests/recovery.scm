@@ -0,0 +1,4 @@
+(if 1)
+(lambda (x))
+(set! 1 2)
+(if 1 2 3 4)
@@ -65,6 +65,44 @@ fn assert_non_exist(path: &Path, regression_error: &mut RegressionError) {
     }
 }
 
+/// Parsing the unparsed program gives the same program except spans
+fn assert_round_trip(program: &Program, path: &str, regression_error: &mut RegressionError) {
+    let unparsed = program.unparse();
+    let difference = match Parser::default().parse(&unparsed, path) {
+        Ok(reparsed) => program
+            .diff(&reparsed)
+            .map(|difference| format!("{:?}", difference)),
+        Err(error) => Some(error.with_color(false).to_string()),
+    };
+    if let Some(difference) = difference {
+        regression_error.insert(|s| {
+            use std::fmt::Write;
+            writeln!(
+                s,
+                "{} does not round trip:\n{}\n{}",
+                path, unparsed, difference
+            )
+            .unwrap();
+        })
+    }
+}
+
+/// Formatting keeps the datums, and formatting again changes nothing
+fn assert_formatted(input: &str, path: &str, regression_error: &mut RegressionError) {
+    let formatter = Formatter::new();
+    let formatted = formatter.format(input, path).unwrap();
+    let same = formatter.format(&formatted, path).unwrap() == formatted
+        && read_all(&formatted, path)
+            .unwrap()
+            .same_structure(&read_all(input, path).unwrap());
+    if !same {
+        regression_error.insert(|s| {
+            use std::fmt::Write;
+            writeln!(s, "{} is not formatted stably:\n{}", path, formatted).unwrap();
+        })
+    }
+}
+
 #[test]
 fn regression() {
     let dir = path!("tests/");
@@ -109,6 +147,7 @@ fn regression() {
                         let content = format!("{:#?}", SpanToSource(&program, &input));
                         assert_eq_or_override(&datum_path, &content, &mut regression_errors);
                         assert_non_exist(&error_path, &mut regression_errors);
+                        assert_formatted(&input, &path_str, &mut regression_errors);
                         true
                     }
                     Err(error) => {
@@ -134,6 +173,7 @@ fn regression() {
                             let content = format!("{:#?}", SpanToSource(&program, &input));
                             assert_eq_or_override(&ast_path, &content, &mut regression_errors);
                             assert_non_exist(&error_path, &mut regression_errors);
+                            assert_round_trip(&program, &path_str, &mut regression_errors);
                         }
                         Err(error) => {
                             assert_non_exist(&ast_path, &mut regression_errors);
@@ -11,6 +11,106 @@ impl<'a, T> SpanToSource<'a, T> {
 }
 
 impl<'a> Debug for SpanToSource<'a, Program> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Program")
+            .field("libraries", &self.replace(&self.0.libraries))
+            .field("imports", &self.replace(&self.0.imports))
+            .field("body", &self.replace(&self.0.body))
+            .finish()
+    }
+}
+
+impl<'a> Debug for SpanToSource<'a, Library> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Library")
+            .field("name", &self.replace(&self.0.name))
+            .field("declarations", &self.replace(&self.0.declarations))
+            .finish()
+    }
+}
+
+impl<'a> Debug for SpanToSource<'a, LibraryDeclaration> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            LibraryDeclaration::Export(specs) => {
+                f.debug_tuple("Export").field(&self.replace(specs)).finish()
+            }
+            LibraryDeclaration::Import(sets) => {
+                f.debug_tuple("Import").field(&self.replace(sets)).finish()
+            }
+            LibraryDeclaration::Begin { defs, exprs } => f
+                .debug_struct("Begin")
+                .field("defs", &self.replace(defs))
+                .field("exprs", &self.replace(exprs))
+                .finish(),
+            LibraryDeclaration::Include {
+                case_insensitive,
+                files,
+            } => f
+                .debug_struct("Include")
+                .field("case_insensitive", case_insensitive)
+                .field("files", &self.replace(files))
+                .finish(),
+            LibraryDeclaration::IncludeLibraryDeclarations(files) => f
+                .debug_tuple("IncludeLibraryDeclarations")
+                .field(&self.replace(files))
+                .finish(),
+        }
+    }
+}
+
+impl<'a> Debug for SpanToSource<'a, ExportSpec> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            ExportSpec::Ident(_) => self.0.fmt(f),
+            ExportSpec::Rename(rename) => f
+                .debug_tuple("Rename")
+                .field(&self.replace(rename))
+                .finish(),
+        }
+    }
+}
+
+impl<'a> Debug for SpanToSource<'a, ImportSet> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            ImportSet::Library(name) => {
+                f.debug_tuple("Library").field(&self.replace(name)).finish()
+            }
+            ImportSet::Only(set, idents) => f
+                .debug_tuple("Only")
+                .field(&self.replace(set))
+                .field(&self.replace(idents))
+                .finish(),
+            ImportSet::Except(set, idents) => f
+                .debug_tuple("Except")
+                .field(&self.replace(set))
+                .field(&self.replace(idents))
+                .finish(),
+            ImportSet::Prefix(set, prefix) => f
+                .debug_tuple("Prefix")
+                .field(&self.replace(set))
+                .field(&self.replace(prefix))
+                .finish(),
+            ImportSet::Rename(set, renames) => f
+                .debug_tuple("Rename")
+                .field(&self.replace(set))
+                .field(&self.replace(renames))
+                .finish(),
+        }
+    }
+}
+
+impl<'a> Debug for SpanToSource<'a, Rename> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Rename")
+            .field(&self.replace(&self.0 .0))
+            .field(&self.replace(&self.0 .1))
+            .finish()
+    }
+}
+
+impl<'a> Debug for SpanToSource<'a, ProcedureBody> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("ProcedureBody")
             .field("defs", &self.replace(&self.0.defs))
@@ -54,7 +154,34 @@ where
 
 impl<'a> Debug for SpanToSource<'a, Definition> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_tuple("Definition")
+        match &self.0 {
+            Definition::Variable(ident, expr) => f
+                .debug_tuple("Variable")
+                .field(&self.replace(ident))
+                .field(&self.replace(expr))
+                .finish(),
+            Definition::Values(formals, expr) => f
+                .debug_tuple("Values")
+                .field(&self.replace(formals))
+                .field(&self.replace(expr))
+                .finish(),
+            Definition::Begin(defs) => f.debug_tuple("Begin").field(&self.replace(defs)).finish(),
+        }
+    }
+}
+
+impl<'a> Debug for SpanToSource<'a, Formals> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Formals")
+            .field("args", &self.replace(&self.0.args))
+            .field("rest", &self.replace(&self.0.rest))
+            .finish()
+    }
+}
+
+impl<'a> Debug for SpanToSource<'a, ValuesBinding> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ValuesBinding")
             .field(&self.replace(&self.0 .0))
             .field(&self.replace(&self.0 .1))
             .finish()
@@ -69,9 +196,10 @@ impl<'a> Debug for SpanToSource<'a, Expression> {
                 .field("operator", &self.replace(operator))
                 .field("args", &self.replace(args))
                 .finish(),
-            Expression::Procedure { args, body } => f
+            Expression::Procedure { args, rest, body } => f
                 .debug_struct("Procedure")
                 .field("args", &self.replace(args))
+                .field("rest", &self.replace(rest))
                 .field("body", &self.replace(body.as_ref()))
                 .finish(),
             Expression::Assignment(ident, expr) => f
@@ -81,6 +209,7 @@ impl<'a> Debug for SpanToSource<'a, Expression> {
                 .finish(),
 
             Expression::Primitive(_) | Expression::Error => self.0.fmt(f),
+            Expression::Quote(datum) => f.debug_tuple("Quote").field(&self.replace(datum)).finish(),
             Expression::Conditional {
                 test,
                 conseq,
@@ -91,6 +220,55 @@ impl<'a> Debug for SpanToSource<'a, Expression> {
                 .field("conseq", &self.replace(conseq))
                 .field("alter", &self.replace(alter))
                 .finish(),
+            Expression::LetValues {
+                sequential,
+                bindings,
+                body,
+            } => f
+                .debug_struct("LetValues")
+                .field("sequential", sequential)
+                .field("bindings", &self.replace(bindings))
+                .field("body", &self.replace(body.as_ref()))
+                .finish(),
+            Expression::Receive {
+                formals,
+                expr,
+                body,
+            } => f
+                .debug_struct("Receive")
+                .field("formals", &self.replace(formals))
+                .field("expr", &self.replace(expr))
+                .field("body", &self.replace(body.as_ref()))
+                .finish(),
+            Expression::Begin { defs, exprs } => f
+                .debug_struct("Begin")
+                .field("defs", &self.replace(defs))
+                .field("exprs", &self.replace(exprs))
+                .finish(),
+            Expression::Guard { var, clauses, body } => f
+                .debug_struct("Guard")
+                .field("var", &self.replace(var))
+                .field("clauses", &self.replace(clauses))
+                .field("body", &self.replace(body.as_ref()))
+                .finish(),
+        }
+    }
+}
+
+impl<'a> Debug for SpanToSource<'a, CondClause> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            CondClause::Test { test, body } => f
+                .debug_struct("Test")
+                .field("test", &self.replace(test))
+                .field("body", &self.replace(body))
+                .finish(),
+            CondClause::Arrow { test, receiver } => f
+                .debug_struct("Arrow")
+                .field("test", &self.replace(test))
+                .field("receiver", &self.replace(receiver))
+                .finish(),
+            CondClause::Else(body) => f.debug_tuple("Else").field(&self.replace(body)).finish(),
         }
     }
 }
@@ -134,6 +312,17 @@ macro_rules! impl_leaf {
 
 impl_non_leaf!(Definition);
 impl_non_leaf!(Expression);
+impl_non_leaf!(CondClause);
+impl_non_leaf!(Formals);
+impl_non_leaf!(ValuesBinding);
+impl_non_leaf!(Library);
+impl_non_leaf!(LibraryDeclaration);
+impl_non_leaf!(ExportSpec);
+impl_non_leaf!(ImportSet);
+impl_non_leaf!(Vec<Spanned<ImportSet>>);
+impl_non_leaf!(Rename);
+impl_non_leaf!(LibraryName);
 impl_non_leaf!(Datum);
 impl_leaf!(Token);
 impl_leaf!(String);
+impl_leaf!(Primitive);
@@ -0,0 +1,64 @@
+use std::collections::HashSet;
+
+use scheme_parser::*;
+
+fn parse(source: &str) -> Program {
+    Parser::default().parse(source, "test.scm").unwrap()
+}
+
+#[test]
+fn ignore_spans() {
+    let a = parse("(define (f x) (if x 1 2))\n(f #t)");
+    let b = parse("(define (f x)\n  ; formatted\n  (if x\n      1\n      2))\n\n(f   #t)");
+    assert_ne!(a, b);
+    assert!(a.same_structure(&b));
+    assert_eq!(a.diff(&b), None);
+    let programs: HashSet<_> = [a, b].into_iter().map(IgnoreSpans).collect();
+    assert_eq!(programs.len(), 1);
+
+    let a = read_all("(a (b \"c\"))", "test.scm").unwrap();
+    let b = read_all("( a ( b  \"c\" ) )", "test.scm").unwrap();
+    assert!(a.same_structure(&b));
+    assert!(IgnoreSpans(&a[0]) == IgnoreSpans(&b[0]));
+}
+
+#[test]
+fn first_difference() {
+    let source = "(define (f x) (if x 1 2)) f";
+    let other = "(define (f x) (if x 1 (g))) f";
+    assert_eq!(
+        parse(source).diff(&parse(other)),
+        Some(Difference {
+            kind: "expression",
+            left: 22..23,
+            right: 22..25,
+        })
+    );
+    assert_eq!(
+        parse("(f 1)").diff(&parse("(f 1 2)")),
+        Some(Difference {
+            kind: "expression",
+            left: 0..5,
+            right: 0..7,
+        })
+    );
+    assert_eq!(
+        parse("(import (scheme base)) 1").diff(&parse("1")),
+        Some(Difference {
+            kind: "program",
+            left: 0..24,
+            right: 0..1,
+        })
+    );
+
+    let a = read_all("(a (b c))", "test.scm").unwrap();
+    let b = read_all("(a (b d))", "test.scm").unwrap();
+    assert_eq!(
+        diff(&a[0], &b[0]),
+        Some(Difference {
+            kind: "datum",
+            left: 6..7,
+            right: 6..7,
+        })
+    );
+}
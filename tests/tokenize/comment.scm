@@ -0,0 +1,9 @@
+  ; consecutive
+  ; indented comments
+
+; after a blank line
+(display 1) ; trailing
+
+  ; between
+(display 2)
+; at the end
\ No newline at end of file
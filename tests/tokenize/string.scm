@@ -0,0 +1,3 @@
+"plain"
+"escaped \"quote\" \\ \n\t"
+"hex \x41; \x3bb;"
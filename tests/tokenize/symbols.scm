@@ -0,0 +1 @@
+(|tmp#1| |a\|b| -0.05 1.50 2.0)
@@ -1,3 +1,3 @@
 al
 as
-```
\ No newline at end of file
+{{{
\ No newline at end of file
@@ -0,0 +1 @@
+"unclosed
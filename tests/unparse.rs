@@ -0,0 +1,28 @@
+use scheme_parser::*;
+
+#[test]
+fn unparse_program() {
+    let source = "(import (only (scheme base) car) (prefix (srfi 1) s:))
+(import (scheme write))
+(define (f x . rest) (if x 'a (g)))
+(define-values (a b) (values 1 -0.5))
+(guard (e ((string? e) => display) (else #f)) (receive all (f \"s\") all))
+(let*-values (((c) (f 1))) (set! c 2) c)";
+    let program = Parser::default().parse(source, "test.scm").unwrap();
+    assert_eq!(
+        program.unparse(),
+        "(import (only (scheme base) car) (prefix (srfi 1) s:))
+(import (scheme write))
+(define f (lambda (x . rest) (if x 'a (g))))
+(define-values (a b) (values 1 -0.5))
+(guard (e ((string? e) => display) (else #f)) (receive all (f \"s\") all))
+(let*-values (((c) (f 1))) (set! c 2) c)
+"
+    );
+    let (expr, _) = expr!((lambda args #(unquote Primitive::Ident("tmp#1".into()))));
+    assert_eq!(expr.unparse(), "(lambda args |tmp#1|)");
+    let reparsed = Parser::default()
+        .parse(&expr.unparse(), "test.scm")
+        .unwrap();
+    assert!(reparsed.body.unwrap().last_expr.0.same_structure(&expr));
+}
@@ -0,0 +1,2 @@
+(let-values (((a 3.5) (values 1 2)))
+  a)
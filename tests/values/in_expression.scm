@@ -0,0 +1 @@
+(foo (define-values (a b) (values 1 2)))
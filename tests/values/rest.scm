@@ -0,0 +1,2 @@
+(receive (a . b c) (values 1 2 3)
+  a)
@@ -0,0 +1,12 @@
+(define-values (q r) (floor/ 7 2))
+(define-values (head . tail) (values 1 2 3))
+(define-values all (values 1 2))
+(let-values (((a b) (values 1 2))
+             ((c . d) (values 3 4 5)))
+  (list a b c d))
+(let*-values (((x) (values 1))
+              (y (values x x)))
+  y)
+(receive (first . others) (values 1 2 3)
+  (define sum (+ first 1))
+  (cons sum others))
@@ -0,0 +1,94 @@
+use scheme_parser::visit::*;
+use scheme_parser::*;
+
+fn parse(source: &str) -> Program {
+    Parser::default().parse(source, "test.scm").unwrap()
+}
+
+#[derive(Default)]
+struct Names<'ast> {
+    bindings: Vec<&'ast str>,
+    variables: Vec<&'ast str>,
+    calls: usize,
+}
+
+impl<'ast> Visitor<'ast> for Names<'ast> {
+    fn visit_expression(&mut self, expr: &'ast Spanned<Expression>) {
+        if let Expression::ProcedureCall { .. } = expr.0 {
+            self.calls += 1;
+        }
+        walk_expression(self, expr);
+    }
+    fn visit_binding(&mut self, (name, _): &'ast Spanned<String>) {
+        self.bindings.push(name);
+    }
+    fn visit_variable(&mut self, name: &'ast str, _: &'ast Span) {
+        self.variables.push(name);
+    }
+}
+
+#[test]
+fn visit_names() {
+    let source = "(define-library (lib) (begin (define a 1)))
+(define (f x . rest) (set! x (g x)) x)
+(define-values (y z) (values 1 2))
+(let-values (((v) (f 1))) v)
+(guard (e ((string? e) => display)) (receive w (f 2) w))";
+    let program = parse(source);
+    let mut names = Names::default();
+    names.visit_program(&program);
+    assert_eq!(
+        names.bindings,
+        ["a", "f", "x", "rest", "y", "z", "v", "e", "w"]
+    );
+    assert_eq!(
+        names.variables,
+        ["x", "g", "x", "x", "values", "f", "v", "string?", "e", "display", "f", "w"]
+    );
+    assert_eq!(names.calls, 5);
+}
+
+struct Rename;
+
+impl VisitorMut for Rename {
+    fn visit_binding(&mut self, (name, _): &mut Spanned<String>) {
+        name.make_ascii_uppercase();
+    }
+    fn visit_variable(&mut self, name: &mut String, _: &mut Span) {
+        name.make_ascii_uppercase();
+    }
+}
+
+/// Replaces `(if test conseq)` with `conseq`
+struct RemoveOneArmedIf;
+
+impl Fold for RemoveOneArmedIf {
+    fn fold_expression(&mut self, expr: Spanned<Expression>) -> Spanned<Expression> {
+        match fold_expression(self, expr) {
+            (
+                Expression::Conditional {
+                    conseq,
+                    alter: None,
+                    ..
+                },
+                _,
+            ) => *conseq,
+            expr => expr,
+        }
+    }
+}
+
+#[test]
+fn rewrite() {
+    let mut program = parse("(define (f x) (if x (if #t (g x)) 1)) (f 1)");
+    Rename.visit_program(&mut program);
+    assert_eq!(
+        program.unparse(),
+        "(define F (lambda (X) (if X (if #t (G X)) 1)))\n(F 1)\n"
+    );
+    let program = RemoveOneArmedIf.fold_program(program);
+    assert_eq!(
+        program.unparse(),
+        "(define F (lambda (X) (if X (G X) 1)))\n(F 1)\n"
+    );
+}
@@ -0,0 +1,43 @@
+use num::BigInt;
+use scheme_parser::*;
+
+fn read(source: &str) -> Datum {
+    read_datum(source, "test.scm").unwrap().0 .0
+}
+
+#[test]
+fn write_reads_back() {
+    for source in [
+        "(define (f x) (if x '(a . b) `(c ,x ,@(d))))",
+        "((\"a \\\"b\\\"\\n\" #t #f) () (1 -2 3.5))",
+        "(lambda args (set! args ...))",
+    ] {
+        let datum = read(source);
+        assert_eq!(datum.to_string(), source);
+        assert_eq!(read(&datum.to_string()), datum);
+    }
+}
+
+#[test]
+fn write_and_display() {
+    let datum = Datum::List(vec![
+        (Primitive::String("a\tb\u{7}".into()).into(), 0..0),
+        (Primitive::Ident("tmp#1".into()).into(), 0..0),
+        (Primitive::Ident("1+".into()).into(), 0..0),
+        (Primitive::Ident("-".into()).into(), 0..0),
+        (
+            Primitive::Real(Real {
+                numer: BigInt::from(-105),
+                denom_log10: 2,
+            })
+            .into(),
+            0..0,
+        ),
+    ]);
+    assert_eq!(datum.to_string(), "(\"a\\tb\\x7;\" |tmp#1| |1+| - -1.05)");
+    assert!(read(&datum.to_string()).same_structure(&datum));
+    assert_eq!(datum.display().to_string(), "(a\tb\u{7} tmp#1 1+ - -1.05)");
+    assert_eq!(datum.summary().to_string(), "(\"a\\tb\\x7;\" ...)");
+    assert_eq!(Datum::List(Vec::new()).summary().to_string(), "()");
+    assert_eq!(Datum::Error.summary().to_string(), "<error>");
+}